@@ -0,0 +1,62 @@
+//! Let AlphaBeta play against itself with two evaluators:
+//! `cargo run --release --example compare_evaluators -- [games] [depth]`
+//!
+//! Each game starts with 2 random moves, then the same opening is played again with the sides swapped
+
+use quarto_game::ai::alphabeta::AlphaBeta;
+use quarto_game::ai::evaluator::{ClassicEvaluator, Evaluator, LiveLinesEvaluator};
+use quarto_game::ai::random::RandomAI;
+use quarto_game::ai::Strategy;
+use quarto_game::board::{Board, BoardState};
+
+const NB_RANDOM_MOVES: usize = 2;
+
+/// Play the game from the board, the `giver` gives the first piece. Return the winner, if any
+fn play(mut board: Board, players: &mut [Box<dyn Strategy>; 2], mut giver: usize) -> Option<usize> {
+    loop {
+        let piece = players[giver].choose_piece_for_opponent(&board);
+        let placer = 1 - giver;
+        let m = players[placer].calc_move(&board, Some(piece)).expect("the ai finds a move");
+        board.play_and_remove_piece(&m).expect("the move of the ai can be played");
+
+        match board.board_state() {
+            BoardState::GameInProgress => giver = placer,
+            BoardState::Win(_) => return Some(placer),
+            BoardState::Loss(_) => return Some(giver),
+            BoardState::Draw => return None,
+        }
+    }
+}
+
+fn strategy(evaluator: impl Evaluator + 'static, depth: usize) -> Box<dyn Strategy> {
+    Box::new(AlphaBeta::with_evaluator(depth, evaluator))
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let games: usize = args.next().map_or(10, |arg| arg.parse().expect("the number of games is a number"));
+    let depth: usize = args.next().map_or(2, |arg| arg.parse().expect("the depth is a number"));
+
+    let names = [ClassicEvaluator.name(), LiveLinesEvaluator.name()];
+    let mut wins = [0; 2];
+    let mut draws = 0;
+    for game in 0..games {
+        let mut opening = Board::create();
+        let mut random = RandomAI::new();
+        for _ in 0..NB_RANDOM_MOVES {
+            let piece = random.choose_piece_for_opponent(&opening);
+            let m = random.calc_move(&opening, Some(piece)).expect("the random ai finds a move");
+            opening.play_and_remove_piece(&m).expect("the random move can be played");
+        }
+
+        // The evaluator `first` gives the first piece after the opening
+        for first in 0..2 {
+            let mut players = [strategy(ClassicEvaluator, depth), strategy(LiveLinesEvaluator, depth)];
+            match play(opening.clone(), &mut players, first) {
+                Some(winner) => wins[winner] += 1,
+                None => draws += 1,
+            }
+        }
+        println!("After {} openings: {} {} / {} {} / {} draws", game + 1, names[0], wins[0], names[1], wins[1], draws);
+    }
+}
@@ -0,0 +1,29 @@
+//! Generate the opening book embedded in the crate:
+//! `cargo run --release --example generate_book -- [max pieces] [depth] [path]`
+
+use std::time::Instant;
+
+use quarto_game::ai::alphabeta::AlphaBeta;
+use quarto_game::ai::book::OpeningBook;
+use quarto_game::rules::Rules;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let max_pieces: usize = args.next().map_or(2, |arg| arg.parse().expect("the max pieces is a number"));
+    let depth: usize = args.next().map_or(4, |arg| arg.parse().expect("the depth is a number"));
+    let path = args.next().unwrap_or_else(|| "books/classic.book".to_string());
+
+    let start = Instant::now();
+    let mut alphabeta = AlphaBeta::new(depth);
+    let book = OpeningBook::generate(Rules::classic(), max_pieces, &mut alphabeta).expect("the book can be generated");
+
+    std::fs::write(&path, book.to_bytes()).expect("the book can be written");
+    println!(
+        "{} positions with at most {} pieces searched at depth {} in {:?}, written in {}",
+        book.len(),
+        max_pieces,
+        depth,
+        start.elapsed(),
+        path
+    );
+}
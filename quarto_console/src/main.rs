@@ -3,10 +3,11 @@
 use ansi_term::{Colour, Style};
 use log::error;
 use quarto_game::{
-    board::{BoardIndex, BoardState, Cell},
-    game::Game,
+    board::{BoardIndex, Cell},
+    game::{Game, Phase},
     piece::Piece,
     player::{Human, PlayerType, AI}, error::ErrorGame,
+    rules::Rules,
 };
 use std::{io};
 
@@ -26,12 +27,22 @@ fn main() {
             format!("Wanna play :\n 1. Player vs Player\n 2. Player vs AI").as_str(),
         ) + 1;
 
+        let rules = match read_input_index(
+            "Rules :\n 1. Classic\n 2. Advanced (2x2 squares also win)\n 3. Toroidal (lines wrap around the edges)\n 4. Call Quarto (a line has to be claimed to win)\n 5. Misère (the player who completes a line loses)",
+        ) {
+            1 => Rules::advanced(),
+            2 => Rules::classic().with_toroidal(true),
+            3 => Rules::classic().with_call_quarto(true),
+            4 => Rules::classic().with_misere(true),
+            _ => Rules::classic(),
+        };
+
         let p1_name = read_input_string("Player 1 name :");
         let mut game = if game_type == 1 {
             let p2_name = read_input_string("Player 2 name :");
-            Game::start(Human::new(p1_name.as_str()), Human::new(p2_name.as_str()))
+            Game::start_with_rules(Human::new(p1_name.as_str()), Human::new(p2_name.as_str()), rules)
         } else {
-            Game::start(Human::new(p1_name.as_str()), AI::new())
+            Game::start_with_rules(Human::new(p1_name.as_str()), AI::new(), rules)
         };
 
         print!(
@@ -40,56 +51,71 @@ fn main() {
             game.get_player(1)
         );
 
-        'game: loop {
-            println!("{}", game.get_board());
-
-            loop {
-                let piece_to_play = choose_piece_for_opponent(&mut game);
+        // The (number of turns, player) of the last call Quarto question, to ask only once
+        let mut last_claim_asked: Option<(usize, usize)> = None;
 
-                // Check if the piece is always available
-                if let Err(e) = piece_to_play {
-                    error!("{}", e);
-                    continue;
+        'game: loop {
+            // When the rules ask for it, a player who can win has to call Quarto
+            if game.rules().call_quarto() && !game.is_over() {
+                if let Some(claimer) = claimer(&game) {
+                    if last_claim_asked != Some((game.history().len(), claimer)) {
+                        last_claim_asked = Some((game.history().len(), claimer));
+                        call_quarto(&mut game, claimer);
+                        continue;
+                    }
                 }
+            }
 
-                let piece_ok = piece_to_play.unwrap();
-                let cell_selected: Cell = play_piece_in_cell(&mut game, &piece_ok);
+            match game.phase().clone() {
+                Phase::AwaitingPieceSelection => {
+                    println!("{}", game.get_board());
 
-                if let Err(e) = game.play(piece_ok, cell_selected) {
-                    println!("{}", e);
-                    continue;
-                } else {
-                    break;
+                    // Check if the piece is always available
+                    if let Err(e) =
+                        choose_piece_for_opponent(&game).and_then(|piece| game.give_piece(piece))
+                    {
+                        error!("{}", e);
+                    }
                 }
-            }
-
-            match game.get_board().board_state() {
-                BoardState::GameInProgress => {
-                    //No winner, let's continue
-                    game.switch_current_player();
+                Phase::AwaitingPlacement(piece) => {
+                    if let Err(e) =
+                        play_piece_in_cell(&game, &piece).and_then(|cell| game.place(cell))
+                    {
+                        println!("{}", e);
+                    }
+                }
+                Phase::AwaitingClaim => {
+                    // Nobody called Quarto on the last piece
+                    if let Err(e) = game.decline_claim() {
+                        println!("{}", e);
+                    }
                 }
-                BoardState::Win(winning_cells) => {
+                Phase::Finished(outcome) => {
                     //We display the board for the last time to show the winning combinaison
                     println!("{}", game.get_board());
 
-                    let win_position: Vec<usize> = winning_cells
-                        .into_iter()
-                        .map(|(position, _)| position + 1)
-                        .collect();
-                    println!(
-                        "{} win the game with combinaison : {:?}",
-                        Style::new()
-                            .bold()
-                            .underline()
-                            .paint(game.current_player().to_string()),
-                        win_position
-                    );
+                    match outcome.winner() {
+                        Some(winner) => {
+                            let win_positions: Vec<Vec<usize>> = outcome
+                                .winning_lines()
+                                .iter()
+                                .map(|line| line.keys().map(|position| position + 1).collect())
+                                .collect();
+                            println!(
+                                "{} win the game in {} turns with combinaison : {:?}",
+                                Style::new()
+                                    .bold()
+                                    .underline()
+                                    .paint(game.get_player(winner).to_string()),
+                                outcome.turns(),
+                                win_positions
+                            );
+                        }
+                        None => println!("Draw ! No winner for this game, well played."),
+                    }
 
                     break 'game;
                 }
-                BoardState::Draw => {
-                    println!("Draw ! No winner for this game, well played.")
-                }
             }
         }
 
@@ -98,15 +124,22 @@ fn main() {
 }
 
 /// Ask to choose a piece for opponent
-fn choose_piece_for_opponent(game: &mut Game) -> Result<Piece, ErrorGame> {
+fn choose_piece_for_opponent(game: &Game) -> Result<Piece, ErrorGame> {
     match game.opponent_player().player_type() {
-        PlayerType::Human => Piece::from_index(game.get_board(), read_input_index(
-            format!(
-                "{} choose a piece for {}\nEnter the piece number : ",
-                game.opponent_player(), game.current_player()
-            )
-            .as_str(),
-        )),
+        PlayerType::Human => {
+            let input = read_input_string(
+                format!(
+                    "{} choose a piece for {}\nEnter the piece number or its code (like DFTS) : ",
+                    game.opponent_player(), game.current_player()
+                )
+                .as_str(),
+            );
+
+            match input.parse::<usize>() {
+                Ok(number) => Piece::from_index(game.get_board(), number.checked_sub(1).ok_or(ErrorGame::IndexOutOfBound)?),
+                Err(_) => input.parse::<Piece>(),
+            }
+        }
         PlayerType::AI => {
             println!("{} is searching a piece...", game.opponent_player());
             let piece = game.opponent_player().choose_piece_for_opponent(game.get_board());
@@ -123,7 +156,7 @@ fn choose_piece_for_opponent(game: &mut Game) -> Result<Piece, ErrorGame> {
 }
 
 /// Ask in which cell the piece has to be played
-fn play_piece_in_cell(game: &mut Game, piece_to_play: &Piece) -> Cell {
+fn play_piece_in_cell(game: &Game, piece_to_play: &Piece) -> Result<Cell, ErrorGame> {
     match game.current_player().player_type() {
         PlayerType::Human => Cell::from_index(
             game.get_board(),
@@ -135,23 +168,70 @@ fn play_piece_in_cell(game: &mut Game, piece_to_play: &Piece) -> Cell {
                 )
                 .as_str(),
             ),
-        )
-        .unwrap(),
+        ),
         PlayerType::AI => {
             let move_selected = game
                 .current_player()
-                .choose_move(*piece_to_play, game.get_board())
-                .unwrap();
+                .choose_move(*piece_to_play, game.get_board())?;
             println!(
                 "{} plays this on cell num {}",
                 game.current_player(),
                 move_selected.cell().to_index() + 1
             );
-            move_selected.cell()
+            Ok(move_selected.cell())
         }
     }
 }
 
+/// Return the player who can call Quarto on the last move
+fn claimer(game: &Game) -> Option<usize> {
+    let last_placer = game.history().last()?.player();
+    match game.phase() {
+        Phase::AwaitingPlacement(_) => Some(1 - last_placer),
+        _ => Some(last_placer),
+    }
+}
+
+/// Ask the player if he wants to call Quarto, and claim the line
+fn call_quarto(game: &mut Game, player: usize) {
+    let last_move = match game.last_move() {
+        Some(last_move) => last_move,
+        None => return,
+    };
+
+    let line = match game.get_player(player).player_type() {
+        PlayerType::Human => {
+            println!("{}", game.get_board());
+            let input = read_input_string(
+                format!(
+                    "{} do you call Quarto ? Enter the 4 cells of the line separated by spaces (or nothing to continue) :",
+                    game.get_player(player)
+                )
+                .as_str(),
+            );
+            if input.is_empty() {
+                return;
+            }
+            input
+                .split_whitespace()
+                .map(|cell| cell.parse::<usize>().map_or(usize::MAX, |n| n.wrapping_sub(1)))
+                .collect()
+        }
+        PlayerType::AI => match game
+            .get_player(player)
+            .claim_quarto(game.get_board(), &last_move)
+        {
+            Some(line) => line,
+            None => return,
+        },
+    };
+
+    println!("{} calls Quarto !", game.get_player(player));
+    if let Err(e) = game.claim_quarto(player, &line) {
+        println!("{}", e);
+    }
+}
+
 /// Read the input from console and return a string
 fn read_input_string(label: &str) -> String {
     let std_input = io::stdin();
@@ -0,0 +1,20 @@
+use std::time::Duration;
+
+use quarto_game::ai::budget::Clock;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = performance, js_name = now)]
+    fn performance_now() -> f64;
+}
+
+/// The clock of the browser (`performance.now()`), `std::time::Instant` is not available in wasm
+#[derive(Debug, Clone, Copy)]
+pub struct BrowserClock;
+
+impl Clock for BrowserClock {
+    fn now(&self) -> Duration {
+        Duration::from_secs_f64(performance_now() / 1000.0)
+    }
+}
@@ -1,9 +1,15 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::clock::BrowserClock;
 use crate::BoardGame;
 use crate::BoardPiece;
-use log::info;
+use log::{error, info};
+use quarto_game::ai::budget::Budget;
 use quarto_game::board::Cell;
 use quarto_game::{
     board::BoardIndex,
+    game::Phase,
     piece::Piece,
     player::{Human, Player, PlayerType, AI},
 };
@@ -15,15 +21,11 @@ pub enum GameMsg {
     GameIsFinish,
 }
 
-pub enum GameState {
-    ChoosePiece,
-    PlayPiece,
-}
+/// How long the AI thinks before each move (the page doesn't respond meanwhile)
+const AI_THINKING_TIME: Duration = Duration::from_millis(500);
 
 pub struct Game {
     game: quarto_game::game::Game,
-    state: GameState,
-    selected_piece: Option<Piece>,
 }
 
 #[derive(PartialEq, Properties, Clone)]
@@ -38,7 +40,7 @@ impl Game {
     pub fn create_player(p_name: String, p_type: PlayerType) -> Box<dyn Player> {
         match p_type {
             PlayerType::Human => Box::new(Human::new(p_name.as_str())),
-            PlayerType::AI => Box::new(AI::new()),
+            PlayerType::AI => Box::new(AI::new().with_budget(Budget::time(AI_THINKING_TIME), Arc::new(BrowserClock))),
         }
     }
 }
@@ -54,11 +56,7 @@ impl Component for Game {
             Game::create_player(ctx.props().p2_name.clone(), ctx.props().p2_type.clone()),
         );
 
-        Self {
-            game: new_game,
-            state: GameState::ChoosePiece,
-            selected_piece: None,
-        }
+        Self { game: new_game }
     }
 
     fn update(&mut self, ctx: &yew::Context<Self>, msg: Self::Message) -> bool {
@@ -69,12 +67,18 @@ impl Component for Game {
                     index_piece,
                     Piece::from_index(self.game.get_board(), index_piece).unwrap()
                 );
-                self.state = GameState::PlayPiece;
-                self.selected_piece = Some(Piece::from_index(self.game.get_board(), index_piece).unwrap());
+                let given = Piece::from_index(self.game.get_board(), index_piece)
+                    .and_then(|piece| self.game.give_piece(piece));
+                if let Err(e) = given {
+                    error!("{}", e);
+                }
             }
             GameMsg::PiecePlayed(index_cell) => {
-                self.game.play(self.selected_piece.unwrap(), Cell::from_index(self.game.get_board(), index_cell).unwrap()).unwrap();
-                self.state = GameState::ChoosePiece;
+                let placed = Cell::from_index(self.game.get_board(), index_cell)
+                    .and_then(|cell| self.game.place(cell));
+                if let Err(e) = placed {
+                    error!("{}", e);
+                }
             }
             GameMsg::GameIsFinish => {}
         }
@@ -99,8 +103,8 @@ impl Component for Game {
             callback_message_piece_played.emit(index_cell);
         });
 
-        let html_state = match self.state {
-            GameState::ChoosePiece => match self.game.opponent_player().player_type() {
+        let html_state = match self.game.phase() {
+            Phase::AwaitingPieceSelection => match self.game.opponent_player().player_type() {
                 PlayerType::Human => {
                     html! {
                         <>
@@ -116,12 +120,12 @@ impl Component for Game {
                     }
                 }
             },
-            GameState::PlayPiece => match self.game.current_player().player_type() {
+            Phase::AwaitingPlacement(piece) => match self.game.current_player().player_type() {
                 PlayerType::Human => {
                     html! {
                         <>
-                            <div>{self.game.opponent_player().name()} { " has choosen the piece " } {self.selected_piece.unwrap().as_text()}</div>
-                            <div>{self.game.current_player().name()} { " where do you play the piece " } {self.selected_piece.unwrap().as_text()}</div>
+                            <div>{self.game.opponent_player().name()} { " has choosen the piece " } {piece.as_text()}</div>
+                            <div>{self.game.current_player().name()} { " where do you play the piece " } {piece.as_text()}</div>
                         </>
                     }
                 }
@@ -133,6 +137,27 @@ impl Component for Game {
                     }
                 }
             },
+            Phase::AwaitingClaim => {
+                html! {
+                    <>
+                        {self.game.current_player().name()} { " can call Quarto !" }
+                    </>
+                }
+            }
+            Phase::Finished(outcome) if !outcome.is_draw() => {
+                html! {
+                    <>
+                        {self.game.get_player(outcome.winner().unwrap()).name()} { " win the game !" }
+                    </>
+                }
+            }
+            Phase::Finished(_) => {
+                html! {
+                    <>
+                        { "Draw ! No winner for this game, well played." }
+                    </>
+                }
+            }
         };
 
         html! {
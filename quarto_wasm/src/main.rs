@@ -1,6 +1,7 @@
 mod pieces;
 mod board;
 mod buttons;
+mod clock;
 mod game;
 
 use game::{Game, GameProps};
@@ -0,0 +1,449 @@
+use crate::ai::budget::Limit;
+use crate::ai::evaluator::{ClassicEvaluator, Evaluator};
+use crate::ai::get_moves;
+use crate::ai::transposition::{Bound, TableStats, TranspositionTable};
+use crate::ai::Board;
+use crate::ai::ErrorGame;
+use crate::ai::Piece;
+use crate::ai::Score;
+use crate::ai::Strategy;
+use crate::r#move::Move;
+
+/// The search works on numbers (`Score::to_value`, the distances counted from the root),
+/// so a score can be compared with "just below" or "just above" another one
+const INFINITY: i64 = i64::MAX;
+
+/// MinMax with alpha-beta pruning: the moves which can't change the result are not searched.
+/// It chooses the same move as `MinMaxTree` with the same depth (the last of the best moves),
+/// but it visits far fewer positions, mostly thanks to the order of the moves.
+/// The values are the ones of the player to move (negamax), a turn is a placement then a gift
+#[derive(Debug, Clone)]
+pub struct AlphaBeta<E: Evaluator = ClassicEvaluator> {
+    depth: usize,
+    /// The score of the last search
+    score: Score,
+    /// The number of positions visited since the creation
+    nodes: usize,
+    /// The bounds of the positions already searched, kept between the searches
+    table: TranspositionTable,
+    /// When the search has to stop before the end (used by `IterativeDeepening`)
+    limit: Option<Limit>,
+    /// The limit has been reached, the result of the search is wrong
+    aborted: bool,
+    /// Score the positions at the end of the search
+    evaluator: E,
+}
+
+impl AlphaBeta {
+    pub fn name() -> String {
+        String::from("AlphaBeta")
+    }
+
+    pub fn new(depth: usize) -> AlphaBeta {
+        AlphaBeta::with_evaluator(depth, ClassicEvaluator)
+    }
+
+    /// The pruning gives the time to search deeper than `MinMaxTree::calc_adequat_depth`:
+    /// one more ply at the end of the game, two more before
+    pub fn calc_adequat_depth(nb_piece_left: usize) -> usize {
+        match nb_piece_left {
+            0..=7 => 3,
+            8..=10 => 5,
+            11..=15 => 6,
+            _ => 0,
+        }
+    }
+}
+
+impl<E: Evaluator> AlphaBeta<E> {
+    /// An AlphaBeta which scores the positions with the evaluator
+    pub fn with_evaluator(depth: usize, evaluator: E) -> AlphaBeta<E> {
+        AlphaBeta {
+            depth,
+            score: Score::default(),
+            nodes: 0,
+            table: TranspositionTable::default(),
+            limit: None,
+            aborted: false,
+            evaluator,
+        }
+    }
+
+    /// Use a transposition table with this number of slots (0 to search without table)
+    pub fn with_table_capacity(mut self, capacity: usize) -> AlphaBeta<E> {
+        self.table = TranspositionTable::new(capacity);
+        self
+    }
+
+    pub fn evaluator(&self) -> &E {
+        &self.evaluator
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The score of the last move or piece chosen
+    pub fn score(&self) -> Score {
+        self.score
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    pub fn table_stats(&self) -> TableStats {
+        self.table.stats()
+    }
+
+    /// Stop the next searches when the limit is reached
+    pub(crate) fn set_limit(&mut self, limit: Option<Limit>) {
+        self.limit = limit;
+        self.aborted = false;
+    }
+
+    /// Has the last search been stopped by the limit ?
+    pub(crate) fn is_aborted(&self) -> bool {
+        self.aborted
+    }
+
+    /// Sort the placements to search the best ones first, so the others are cut sooner:
+    /// - the placements which end the game the way the player wants (a Quarto, unless the rules are misère),
+    /// - then the placements which leave no piece able to complete a line,
+    /// - then the others.
+    ///
+    /// The sort is stable, the moves of the same kind keep their order
+    fn order_moves(board: &mut Board, moves: Vec<Move>) -> Vec<(usize, Move)> {
+        let mut moves: Vec<(usize, usize, Move)> = moves
+            .into_iter()
+            .enumerate()
+            .map(|(index, m)| {
+                board.play_and_remove_piece(&m).unwrap();
+                let completes_line = !board.completed_lines(m.cell().to_index()).is_empty();
+                let safe = board.winning_pieces_mask() == 0;
+                board.unplay(&m).unwrap();
+
+                // A line is a win, a loss with the misère rules
+                let wanted = completes_line && !board.rules().misere();
+                let kind = if wanted {
+                    0
+                } else if completes_line {
+                    3
+                } else if safe {
+                    1
+                } else {
+                    2
+                };
+                (kind, index, m)
+            })
+            .collect();
+        moves.sort_by_key(|(kind, _, _)| *kind);
+        moves.into_iter().map(|(_, index, m)| (index, m)).collect()
+    }
+
+    /// Sort the pieces to give: the ones which can't complete a line first (with the misère rules, they all can be given)
+    fn order_gifts(board: &Board) -> Vec<(usize, Piece)> {
+        let winning = if board.rules().misere() { 0 } else { board.winning_pieces_mask() };
+        let mut gifts: Vec<(usize, Piece)> = board.get_available_pieces().into_values().enumerate().collect();
+        gifts.sort_by_key(|(_, piece)| winning & (1 << piece.number()) != 0);
+        gifts
+    }
+
+    /// The alpha-beta search of the turn of the player who places the piece, `ply` placements after the root.
+    /// The board is restored before returning.
+    /// The result is exact between alpha and beta, otherwise it's only a bound:
+    /// the real value is lower when the result is <= alpha, higher when it's >= beta
+    fn search(&mut self, board: &mut Board, piece: Piece, depth: usize, ply: usize, mut alpha: i64, mut beta: i64) -> i64 {
+        self.nodes += 1;
+        if self.aborted || self.limit.as_ref().is_some_and(|limit| limit.is_reached(self.nodes)) {
+            // The value doesn't matter, the result of a stopped search is thrown away
+            self.aborted = true;
+            return 0;
+        }
+        if depth == 0 || Score::game_over(board).is_some() {
+            return self.evaluator.evaluate_turn(board, Some(piece)).to_value(ply);
+        }
+        // A Quarto now is the best result, no need to search further
+        if !board.rules().misere() && board.winning_pieces_mask() & (1 << piece.number()) != 0 {
+            return Score::Win(1).to_value(ply);
+        }
+
+        let (original_alpha, original_beta) = (alpha, beta);
+        if let Some(entry) = self.table.probe(board, Some(piece), depth) {
+            let value = entry.score().to_value(ply);
+            match entry.bound() {
+                Bound::Exact => return value,
+                Bound::Lower if value >= beta => return value,
+                Bound::Upper if value <= alpha => return value,
+                Bound::Lower => alpha = alpha.max(value),
+                Bound::Upper => beta = beta.min(value),
+            }
+        }
+
+        let mut best = -INFINITY;
+        for (_, m) in Self::order_moves(board, board.get_available_moves_from_piece(piece)) {
+            let value = self.placement(board, &m, depth, ply, alpha, beta);
+            best = best.max(value);
+            alpha = alpha.max(value);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best <= original_alpha {
+            Bound::Upper
+        } else if best >= original_beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        if !self.aborted {
+            self.table.store(board, Some(piece), depth, Score::from_value(best, ply), bound);
+        }
+        best
+    }
+
+    /// The value of the placement for its player, who then gives the best piece to the opponent.
+    /// `depth` counts the placement, the board is restored before returning
+    fn placement(&mut self, board: &mut Board, m: &Move, depth: usize, ply: usize, mut alpha: i64, beta: i64) -> i64 {
+        board.play_and_remove_piece(m).unwrap();
+
+        let value = if let Some(score) = Score::game_over(board) {
+            -score.to_value(ply + 1)
+        } else if depth == 1 {
+            // The turns of the opponent are leaves: every piece is as good as another, unless it completes a line
+            let gifts = Self::order_gifts(board);
+            let (_, piece) = gifts[0];
+            -self.evaluator.evaluate_turn(board, Some(piece)).to_value(ply + 1)
+        } else {
+            let mut best = -INFINITY;
+            for (_, piece) in Self::order_gifts(board) {
+                let value = -self.search(board, piece, depth - 1, ply + 1, -beta, -alpha);
+                best = best.max(value);
+                alpha = alpha.max(value);
+                if alpha >= beta {
+                    break;
+                }
+            }
+            best
+        };
+
+        board.unplay(m).unwrap();
+        value
+    }
+
+    /// Search the children of the root in the order of the sort, and keep the last of the best ones
+    /// in the original order, like `MinMaxTree`. A child is only searched exactly when it can become the selected one
+    fn select<T: Copy>(&mut self, children: Vec<(usize, T)>, mut value: impl FnMut(&mut Self, T, i64, i64) -> i64) -> Option<(T, i64)> {
+        let mut selected: Option<(usize, T, i64)> = None;
+        for (index, child) in children {
+            // A child before the selected one has to be strictly better to replace it
+            let alpha = match selected {
+                None => -INFINITY,
+                Some((selected_index, _, best)) if index > selected_index => best - 1,
+                Some((_, _, best)) => best,
+            };
+
+            let value = value(self, child, alpha, INFINITY);
+            if value > alpha {
+                selected = Some((index, child, value));
+            }
+        }
+        selected.map(|(_, child, value)| (child, value))
+    }
+
+    /// The best move with the piece (or any piece) for a search at this depth, with its score
+    pub(crate) fn search_move(&mut self, board: &Board, piece: Option<Piece>, depth: usize) -> Option<(Move, Score)> {
+        if depth == 0 {
+            return None;
+        }
+        let mut board = board.clone();
+        board.set_hand(None);
+        let moves = get_moves(&board, piece);
+        let moves = Self::order_moves(&mut board, moves);
+        self.select(moves, |alphabeta, m, alpha, beta| alphabeta.placement(&mut board, &m, depth, 0, alpha, beta))
+            .map(|(m, value)| (m, Score::from_value(value, 0)))
+    }
+
+    /// The best piece to give, the one whose turn is the worst for the opponent, for a search at this depth, with its score.
+    /// None when no piece is left
+    pub(crate) fn search_gift(&mut self, board: &Board, depth: usize) -> Option<(Piece, Score)> {
+        let mut board = board.clone();
+        board.set_hand(None);
+        let gifts = Self::order_gifts(&board);
+        self.select(gifts, |alphabeta, piece, alpha, beta| -alphabeta.search(&mut board, piece, depth, 0, -beta, -alpha))
+            .map(|(piece, value)| (piece, Score::from_value(value, 0)))
+    }
+}
+
+impl<E: Evaluator> Strategy for AlphaBeta<E> {
+    fn name(&self) -> String {
+        AlphaBeta::name()
+    }
+
+    fn calc_move(&mut self, board: &Board, piece: Option<Piece>) -> Result<Move, ErrorGame> {
+        let (selected_move, score) =
+            self.search_move(board, piece, self.depth)
+                .ok_or_else(|| ErrorGame::NoBestMove {
+                    depth: self.depth,
+                    position: board.to_notation(),
+                })?;
+        self.score = score;
+
+        Ok(selected_move)
+    }
+
+    /// Like `MinMaxTree`: the piece is chosen by the same search, the worst turn for the opponent
+    fn choose_piece_for_opponent(&mut self, board: &Board) -> Piece {
+        match self.search_gift(board, self.depth) {
+            Some((piece, score)) => {
+                self.score = score;
+                info!("best score = {} which is piece = {}", score, piece);
+                piece
+            }
+            None => *board.get_available_pieces().values().next().unwrap(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::minmax_tree::MinMaxTree;
+    use crate::board::Cell;
+    use crate::rules::Rules;
+
+    fn board_from(moves: &[(&str, usize)], rules: Rules) -> Board {
+        let mut board = Board::create_with_rules(rules);
+        for (piece, cell) in moves {
+            board
+                .play_and_remove_piece(&Move::new(Piece::from(*piece), Cell::new(*cell).unwrap()))
+                .unwrap();
+        }
+        board
+    }
+
+    fn positions() -> Vec<Board> {
+        let moves = [
+            ("DFXC", 0),
+            ("DETS", 1),
+            ("WFTC", 6),
+            ("DFTC", 7),
+            ("WEXS", 9),
+            ("WETC", 10),
+            ("DEXS", 12),
+        ];
+        vec![
+            board_from(&moves[..4], Rules::classic()),
+            board_from(&moves[..5], Rules::classic()),
+            board_from(&moves, Rules::classic()),
+            board_from(&moves[..6], Rules::advanced()),
+            board_from(&moves[..6], Rules::classic().with_misere(true)),
+        ]
+    }
+
+    #[test]
+    fn test_same_move_as_minmax_tree() {
+        for board in positions() {
+            let piece = *board.get_available_pieces().values().last().unwrap();
+            for depth in 1..=3 {
+                let mut minmax_tree = MinMaxTree::new(depth);
+                let mut alphabeta = AlphaBeta::new(depth);
+
+                assert_eq!(
+                    alphabeta.calc_move(&board, Some(piece)).unwrap(),
+                    minmax_tree.calc_move(&board, Some(piece)).unwrap(),
+                    "depth {} in {}",
+                    depth,
+                    board.to_notation()
+                );
+                assert_eq!(alphabeta.score(), minmax_tree.score());
+            }
+        }
+    }
+
+    #[test]
+    fn test_same_move_as_minmax_tree_with_another_evaluator() {
+        use crate::ai::evaluator::LiveLinesEvaluator;
+
+        for board in positions() {
+            let piece = *board.get_available_pieces().values().last().unwrap();
+            let mut minmax_tree = MinMaxTree::with_evaluator(2, LiveLinesEvaluator);
+            let mut alphabeta = AlphaBeta::with_evaluator(2, LiveLinesEvaluator);
+
+            assert_eq!(
+                alphabeta.calc_move(&board, Some(piece)).unwrap(),
+                minmax_tree.calc_move(&board, Some(piece)).unwrap()
+            );
+            assert_eq!(alphabeta.score(), minmax_tree.score());
+        }
+    }
+
+    #[test]
+    fn test_same_score_without_table() {
+        for board in positions() {
+            let piece = *board.get_available_pieces().values().next().unwrap();
+            let mut with_table = AlphaBeta::new(3);
+            let mut without_table = AlphaBeta::new(3).with_table_capacity(0);
+
+            assert_eq!(
+                with_table.calc_move(&board, Some(piece)).unwrap(),
+                without_table.calc_move(&board, Some(piece)).unwrap()
+            );
+            assert_eq!(with_table.score(), without_table.score());
+        }
+    }
+
+    #[test]
+    fn test_visit_fewer_nodes() {
+        // The number of positions visited by the minmax, leaves included: a placement then a piece to give
+        fn count_nodes(board: &mut Board, moves: Vec<Move>, depth: usize) -> usize {
+            moves.iter().fold(1, |nodes, m| {
+                board.play_and_remove_piece(m).unwrap();
+                let nodes = if depth == 1 || Score::game_over(board).is_some() {
+                    nodes + 1
+                } else {
+                    board.get_available_pieces().into_values().fold(nodes, |nodes, piece| {
+                        nodes + count_nodes(board, board.get_available_moves_from_piece(piece), depth - 1)
+                    })
+                };
+                board.unplay(m).unwrap();
+                nodes
+            })
+        }
+
+        let board = positions().remove(1);
+        let mut alphabeta = AlphaBeta::new(3).with_table_capacity(0);
+        alphabeta.calc_move(&board, None).unwrap();
+
+        let minmax_nodes = count_nodes(&mut board.clone(), board.get_available_moves(), 3);
+        assert!(alphabeta.nodes() * 4 < minmax_nodes, "{} / {}", alphabeta.nodes(), minmax_nodes);
+    }
+
+    #[test]
+    fn test_no_gift_when_no_piece_is_left() {
+        // The board of a draw, every piece has been played
+        let board = Board::from_notation("WETSDFTCDFTSDFXS/WFTSWFXSDETSDFXC/DEXSWEXCWFXCWETC/DETCWFTCWEXSDEXC - 1").unwrap();
+        assert!(board.get_available_pieces().is_empty());
+        assert_eq!(AlphaBeta::new(2).search_gift(&board, 2), None);
+    }
+
+    #[test]
+    fn test_choose_piece_like_minmax_tree() {
+        for board in positions() {
+            for depth in 1..=2 {
+                let mut minmax_tree = MinMaxTree::new(depth);
+                let mut alphabeta = AlphaBeta::new(depth);
+
+                assert_eq!(
+                    alphabeta.choose_piece_for_opponent(&board),
+                    minmax_tree.choose_piece_for_opponent(&board),
+                    "depth {} in {}",
+                    depth,
+                    board.to_notation()
+                );
+                assert_eq!(alphabeta.score(), minmax_tree.score());
+            }
+        }
+    }
+}
@@ -0,0 +1,361 @@
+//! # Opening book
+//! The first turns are searched once, deeper than during a game, and the answers are kept in a book.
+//! The positions are deduplicated with the symmetries: only the canonical board is kept, with the piece in hand.
+//!
+//! The book is saved in a small binary format (all the numbers are little endian):
+//! - the magic `QBK` and the version of the format (1 byte)
+//! - the length of the rules text (1 byte), then the rules as written by `Display`
+//! - the maximum number of pieces on the board of a position (1 byte)
+//! - the number of entries (4 bytes), then each entry sorted by key:
+//!   the Zobrist hash of the canonical board (8 bytes) and the answer (1 byte),
+//!   the cell index when a piece is in hand, the piece number otherwise.
+//!
+//! The answers are the ones of the canonical board, they are played on the real board with the transform back.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, OnceLock};
+
+use crate::ai::Board;
+use crate::ai::ErrorGame;
+use crate::ai::Piece;
+use crate::ai::Strategy;
+use crate::board::Cell;
+use crate::r#move::Move;
+use crate::rules::Rules;
+
+const MAGIC: &[u8; 3] = b"QBK";
+const VERSION: u8 = 1;
+
+/// The book generated with `examples/generate_book.rs` for the classic rules
+const EMBEDDED_BOOK: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/books/classic.book"));
+
+/// The answers of the first turns, for a set of rules
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpeningBook {
+    rules: Rules,
+    max_pieces: usize,
+    entries: HashMap<u64, u8>,
+}
+
+impl OpeningBook {
+    /// An empty book. Calling "Quarto!" doesn't change the best moves, so the book doesn't care about it
+    pub fn new(rules: Rules, max_pieces: usize) -> OpeningBook {
+        OpeningBook {
+            rules: rules.with_call_quarto(false),
+            max_pieces,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// The book of the classic rules shipped with the crate
+    pub fn embedded() -> Arc<OpeningBook> {
+        static BOOK: OnceLock<Arc<OpeningBook>> = OnceLock::new();
+        Arc::clone(BOOK.get_or_init(|| {
+            Arc::new(OpeningBook::from_bytes(EMBEDDED_BOOK).expect("the embedded opening book can be read"))
+        }))
+    }
+
+    /// Search the answer of every position with at most `max_pieces` pieces on the board.
+    /// The positions are explored from the empty board, each canonical position is searched only once
+    pub fn generate(rules: Rules, max_pieces: usize, strategy: &mut dyn Strategy) -> Result<OpeningBook, ErrorGame> {
+        let mut book = OpeningBook::new(rules, max_pieces);
+        let mut seen = HashSet::new();
+        let mut positions = VecDeque::from([Board::create_with_rules(book.rules).canonical().0]);
+
+        while let Some(position) = positions.pop_front() {
+            let mut board = position.clone();
+            board.set_hand(None);
+
+            match position.hand() {
+                None => {
+                    let piece = strategy.choose_piece_for_opponent(&board);
+                    book.entries.insert(position.zobrist(), piece.number());
+
+                    for piece in position.get_available_pieces().into_values() {
+                        let mut next = position.clone();
+                        next.set_hand(Some(piece));
+                        let (canonical, _) = next.canonical();
+                        if seen.insert(canonical.zobrist()) {
+                            positions.push_back(canonical);
+                        }
+                    }
+                }
+                Some(piece) => {
+                    let m = strategy.calc_move(&board, Some(piece))?;
+                    book.entries.insert(position.zobrist(), m.cell().to_index() as u8);
+
+                    if book.nb_pieces(&position) >= max_pieces {
+                        continue;
+                    }
+                    for m in position.get_available_moves_from_piece(piece) {
+                        let mut next = position.clone();
+                        next.play_and_remove_piece(&m)?;
+                        if !next.completed_lines(m.cell().to_index()).is_empty() || !next.can_play_another_turn() {
+                            continue;
+                        }
+                        let (canonical, _) = next.canonical();
+                        if seen.insert(canonical.zobrist()) {
+                            positions.push_back(canonical);
+                        }
+                    }
+                }
+            }
+        }
+
+        info!("Opening book generated with {} positions", book.len());
+        Ok(book)
+    }
+
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
+    /// The maximum number of pieces on the board of the positions of the book
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn nb_pieces(&self, board: &Board) -> usize {
+        board.get_cells().len() - board.get_empty_cells().len()
+    }
+
+    /// Can the position be in the book ? The rules must be the same, and the game must not be too far
+    pub fn covers(&self, board: &Board) -> bool {
+        board.rules().with_call_quarto(false) == self.rules && self.nb_pieces(board) <= self.max_pieces
+    }
+
+    /// The placement of the book for this piece, if the position is in it
+    pub fn placement(&self, board: &Board, piece: Piece) -> Option<Move> {
+        if !self.covers(board) {
+            return None;
+        }
+
+        let mut board = board.clone();
+        board.set_hand(Some(piece));
+        let (canonical, transform) = board.canonical();
+        let cell = Cell::new(*self.entries.get(&canonical.zobrist())? as usize).ok()?;
+        let m = transform.revert_move(&Move::new(canonical.hand()?, cell));
+
+        // The hash could collide with a position which isn't in the book
+        board.get_available_moves_from_piece(piece).contains(&m).then_some(m)
+    }
+
+    /// The piece of the book to give to the opponent, if the position is in it
+    pub fn gift(&self, board: &Board) -> Option<Piece> {
+        if !self.covers(board) {
+            return None;
+        }
+
+        let mut board = board.clone();
+        board.set_hand(None);
+        let (canonical, transform) = board.canonical();
+        let piece = Piece::from_number(*self.entries.get(&canonical.zobrist())?).ok()?;
+        let piece = transform.inverse().apply_piece(piece);
+
+        board.is_available(piece.number() as usize).then_some(piece)
+    }
+
+    /// Write the book in the binary format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let rules = self.rules.to_string();
+        let mut entries: Vec<(&u64, &u8)> = self.entries.iter().collect();
+        entries.sort();
+
+        let mut bytes = Vec::with_capacity(MAGIC.len() + rules.len() + 7 + entries.len() * 9);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(rules.len() as u8);
+        bytes.extend_from_slice(rules.as_bytes());
+        bytes.push(self.max_pieces as u8);
+        bytes.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+        for (key, answer) in entries {
+            bytes.extend_from_slice(&key.to_le_bytes());
+            bytes.push(*answer);
+        }
+        bytes
+    }
+
+    /// Read a book written by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<OpeningBook, ErrorGame> {
+        let mut reader = Reader { bytes };
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(ErrorGame::InvalidBook("this is not an opening book".to_string()));
+        }
+        let version = reader.take(1)?[0];
+        if version != VERSION {
+            return Err(ErrorGame::InvalidBook(format!("the version {} is not supported", version)));
+        }
+        let rules_len = reader.take(1)?[0] as usize;
+        let rules = std::str::from_utf8(reader.take(rules_len)?)
+            .map_err(|_| ErrorGame::InvalidBook("the rules are not a text".to_string()))?
+            .parse::<Rules>()?;
+        let max_pieces = reader.take(1)?[0] as usize;
+        let count = u32::from_le_bytes(reader.take(4)?.try_into().unwrap()) as usize;
+
+        let mut book = OpeningBook::new(rules, max_pieces);
+        for _ in 0..count {
+            let key = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+            book.entries.insert(key, reader.take(1)?[0]);
+        }
+        if !reader.bytes.is_empty() {
+            return Err(ErrorGame::InvalidBook("there are bytes after the entries".to_string()));
+        }
+
+        Ok(book)
+    }
+}
+
+/// Read the bytes of a book one field after the other
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ErrorGame> {
+        if self.bytes.len() < len {
+            return Err(ErrorGame::InvalidBook("the book is truncated".to_string()));
+        }
+        let (field, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(field)
+    }
+}
+
+/// Play the answers of the book, and let another strategy play when the position is not in it
+pub struct BookStrategy {
+    book: Arc<OpeningBook>,
+    fallback: Box<dyn Strategy>,
+}
+
+impl BookStrategy {
+    pub fn name() -> String {
+        String::from("Book")
+    }
+
+    pub fn new(book: Arc<OpeningBook>, fallback: Box<dyn Strategy>) -> BookStrategy {
+        BookStrategy { book, fallback }
+    }
+}
+
+impl Strategy for BookStrategy {
+    fn name(&self) -> String {
+        BookStrategy::name()
+    }
+
+    fn calc_move(&mut self, board: &Board, piece: Option<Piece>) -> Result<Move, ErrorGame> {
+        match piece.and_then(|piece| self.book.placement(board, piece)) {
+            Some(m) => {
+                info!("Move {} found in the opening book", m);
+                Ok(m)
+            }
+            None => self.fallback.calc_move(board, piece),
+        }
+    }
+
+    fn choose_piece_for_opponent(&mut self, board: &Board) -> Piece {
+        match self.book.gift(board) {
+            Some(piece) => {
+                info!("Piece {} found in the opening book", piece);
+                piece
+            }
+            None => self.fallback.choose_piece_for_opponent(board),
+        }
+    }
+
+    fn claim_quarto(&mut self, board: &Board, last_move: &Move) -> Option<Vec<usize>> {
+        self.fallback.claim_quarto(board, last_move)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::alphabeta::AlphaBeta;
+    use crate::ai::random::RandomAI;
+    use crate::symmetry::Transform;
+
+    fn small_book() -> OpeningBook {
+        OpeningBook::generate(Rules::classic(), 1, &mut AlphaBeta::new(1)).unwrap()
+    }
+
+    #[test]
+    fn test_bytes_roundtrip() {
+        let book = small_book();
+        assert!(!book.is_empty());
+
+        let bytes = book.to_bytes();
+        assert_eq!(OpeningBook::from_bytes(&bytes).unwrap(), book);
+
+        assert!(OpeningBook::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(OpeningBook::from_bytes(b"QBK\x02").is_err());
+        assert_eq!(
+            OpeningBook::from_bytes(b"nope").unwrap_err().kind(),
+            crate::error::ErrorKind::InvalidNotation
+        );
+    }
+
+    #[test]
+    fn test_same_answer_on_symmetric_positions() {
+        let book = small_book();
+        let mut board = Board::create();
+        let piece = Piece::from("WFTC");
+        board
+            .play_and_remove_piece(&Move::new(Piece::from("DEXS"), Cell::new(5).unwrap()))
+            .unwrap();
+        let m = book.placement(&board, piece).unwrap();
+
+        let mut played = board.clone();
+        played.play_and_remove_piece(&m).unwrap();
+
+        // The answer can be another cell than the transformed one, but only a symmetric one
+        for transform in Transform::all(board.lines()).iter().step_by(101) {
+            let transformed = transform.apply_board(&board);
+            let found = book.placement(&transformed, transform.apply_piece(piece)).unwrap();
+            let mut transformed_played = transformed.clone();
+            transformed_played.play_and_remove_piece(&found).unwrap();
+            assert_eq!(transformed_played.canonical().0.zobrist(), played.canonical().0.zobrist());
+        }
+
+        let piece = book.gift(&Board::create()).unwrap();
+        assert!(Board::create().is_available(piece.number() as usize));
+    }
+
+    #[test]
+    fn test_fallback_out_of_book() {
+        let book = Arc::new(small_book());
+        let mut board = Board::create();
+        for (piece, cell) in [("DEXS", 5), ("WFTC", 10)] {
+            board
+                .play_and_remove_piece(&Move::new(Piece::from(piece), Cell::new(cell).unwrap()))
+                .unwrap();
+        }
+        assert!(!book.covers(&board));
+        assert!(book.placement(&board, Piece::from("DFTC")).is_none());
+
+        // Other rules are never in the book
+        assert!(!book.covers(&Board::create_with_rules(Rules::advanced())));
+        assert!(book.covers(&Board::create_with_rules(Rules::classic().with_call_quarto(true))));
+
+        let mut strategy = BookStrategy::new(book, Box::new(RandomAI::new()));
+        let m = strategy.calc_move(&board, Some(Piece::from("DFTC"))).unwrap();
+        assert!(board.get_available_moves_from_piece(Piece::from("DFTC")).contains(&m));
+    }
+
+    #[test]
+    fn test_embedded_book() {
+        let book = OpeningBook::embedded();
+        assert_eq!(book.rules(), Rules::classic());
+        assert!(book.max_pieces() >= 2);
+        assert!(book.gift(&Board::create()).is_some());
+        assert!(book.placement(&Board::create(), Piece::from("DFTC")).is_some());
+    }
+}
@@ -0,0 +1,149 @@
+//! # Thinking budget
+//! How long a search can think, in time or in visited positions.
+//! `std::time::Instant` panics in the browser, so the time comes from a `Clock`
+//! given by the front end (`SystemClock` for the native ones).
+
+use std::{fmt::Debug, sync::Arc, time::Duration};
+
+/// A source of time. Only the difference between two calls matters, the origin can be anything
+pub trait Clock: Debug + Send + Sync {
+    /// The time elapsed since the origin of the clock
+    fn now(&self) -> Duration;
+}
+
+/// The clock of the system, from the creation of the clock. It's not available in wasm
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    origin: std::time::Instant,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SystemClock {
+    pub fn new() -> SystemClock {
+        SystemClock {
+            origin: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock::new()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+/// The limits of a search, the first one reached stops it
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Budget {
+    time: Option<Duration>,
+    nodes: Option<usize>,
+}
+
+impl Budget {
+    /// Think during this time at most
+    pub fn time(time: Duration) -> Budget {
+        Budget {
+            time: Some(time),
+            nodes: None,
+        }
+    }
+
+    /// Visit this number of positions at most
+    pub fn nodes(nodes: usize) -> Budget {
+        Budget {
+            time: None,
+            nodes: Some(nodes),
+        }
+    }
+
+    pub fn with_time(mut self, time: Duration) -> Budget {
+        self.time = Some(time);
+        self
+    }
+
+    pub fn with_nodes(mut self, nodes: usize) -> Budget {
+        self.nodes = Some(nodes);
+        self
+    }
+
+    /// Start to spend the budget now
+    pub(crate) fn start(&self, clock: &Arc<dyn Clock>, nodes: usize) -> Limit {
+        Limit {
+            clock: Arc::clone(clock),
+            end: self.time.map(|time| clock.now() + time),
+            max_nodes: self.nodes.map(|max_nodes| nodes + max_nodes),
+        }
+    }
+}
+
+/// A budget being spent: when the search has to stop
+#[derive(Debug, Clone)]
+pub(crate) struct Limit {
+    clock: Arc<dyn Clock>,
+    end: Option<Duration>,
+    max_nodes: Option<usize>,
+}
+
+impl Limit {
+    /// The clock is not asked at each position, it costs more than the visit of the position in the browser
+    const NODES_BETWEEN_CLOCK_CHECKS: usize = 256;
+
+    /// Is the budget spent, with this number of positions visited ?
+    pub(crate) fn is_reached(&self, nodes: usize) -> bool {
+        self.max_nodes.is_some_and(|max_nodes| nodes >= max_nodes)
+            || (nodes.is_multiple_of(Self::NODES_BETWEEN_CLOCK_CHECKS) && self.end.is_some_and(|end| self.clock.now() >= end))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use super::*;
+
+    /// A clock which moves forward of one millisecond each time it's asked
+    #[derive(Debug, Default)]
+    pub(crate) struct FakeClock {
+        millis: AtomicU64,
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Duration {
+            Duration::from_millis(self.millis.fetch_add(1, Ordering::Relaxed))
+        }
+    }
+
+    #[test]
+    fn test_budget_limit() {
+        let clock: Arc<dyn Clock> = Arc::new(FakeClock::default());
+
+        let limit = Budget::nodes(1000).start(&clock, 500);
+        assert!(!limit.is_reached(1499));
+        assert!(limit.is_reached(1500));
+
+        // The clock starts at 0 and the budget at 1ms: the end is at 1ms
+        let limit = Budget::time(Duration::from_millis(1)).start(&clock, 0);
+        assert!(!limit.is_reached(1));
+        assert!(limit.is_reached(256));
+
+        assert!(!Budget::default().start(&clock, 0).is_reached(usize::MAX - 255));
+    }
+
+    #[test]
+    fn test_system_clock() {
+        let clock = SystemClock::new();
+        let start = clock.now();
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(clock.now() >= start + Duration::from_millis(2));
+    }
+}
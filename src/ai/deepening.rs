@@ -0,0 +1,177 @@
+use std::sync::Arc;
+
+use crate::ai::alphabeta::AlphaBeta;
+use crate::ai::budget::{Budget, Clock};
+use crate::ai::Board;
+use crate::ai::ErrorGame;
+use crate::ai::Piece;
+use crate::ai::Strategy;
+use crate::r#move::Move;
+
+/// Alpha-beta searches deeper and deeper until the budget is spent.
+/// The result is the one of the last search which has finished, the depth fits the position
+/// instead of being chosen from the number of pieces played.
+/// The search at depth 1 always finishes, whatever the budget
+#[derive(Debug, Clone)]
+pub struct IterativeDeepening {
+    budget: Budget,
+    clock: Arc<dyn Clock>,
+    /// Keeps its transposition table from a search to the next one
+    alphabeta: AlphaBeta,
+    /// The depth of the last search which has finished
+    depth: usize,
+}
+
+impl IterativeDeepening {
+    pub fn name() -> String {
+        String::from("IterativeDeepening")
+    }
+
+    /// Use the clock of the system
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new(budget: Budget) -> IterativeDeepening {
+        IterativeDeepening::with_clock(budget, Arc::new(crate::ai::budget::SystemClock::new()))
+    }
+
+    /// Use another clock, for example the one of the browser
+    pub fn with_clock(budget: Budget, clock: Arc<dyn Clock>) -> IterativeDeepening {
+        IterativeDeepening {
+            budget,
+            clock,
+            alphabeta: AlphaBeta::new(0),
+            depth: 0,
+        }
+    }
+
+    /// The depth reached by the last search
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The number of positions visited since the creation
+    pub fn nodes(&self) -> usize {
+        self.alphabeta.nodes()
+    }
+
+    /// Search at the depths 1, 2, ... until the budget is spent or the end of the game is reached,
+    /// and return the result of the last search which has finished.
+    /// The deepening also stops when `search` has no result for a depth
+    fn deepen<T>(&mut self, board: &Board, mut search: impl FnMut(&mut AlphaBeta, usize) -> Option<T>) -> Option<T> {
+        let limit = self.budget.start(&self.clock, self.alphabeta.nodes());
+        let mut result = None;
+        self.depth = 0;
+
+        for depth in 1..=board.get_empty_cells().len() {
+            self.alphabeta.set_limit((depth > 1).then(|| limit.clone()));
+            let Some(depth_result) = search(&mut self.alphabeta, depth) else {
+                break;
+            };
+            if self.alphabeta.is_aborted() {
+                info!("Budget spent during the search at depth {}", depth);
+                break;
+            }
+
+            self.depth = depth;
+            result = Some(depth_result);
+        }
+
+        self.alphabeta.set_limit(None);
+        result
+    }
+}
+
+impl Strategy for IterativeDeepening {
+    fn name(&self) -> String {
+        IterativeDeepening::name()
+    }
+
+    fn calc_move(&mut self, board: &Board, piece: Option<Piece>) -> Result<Move, ErrorGame> {
+        let result = self.deepen(board, |alphabeta, depth| alphabeta.search_move(board, piece, depth));
+        info!("Move searched until depth = {}", self.depth);
+
+        result.map(|(m, _)| m).ok_or_else(|| ErrorGame::NoBestMove {
+            depth: self.depth,
+            position: board.to_notation(),
+        })
+    }
+
+    /// The piece of the deepest search, a lost game is delayed as much as the depth can see
+    fn choose_piece_for_opponent(&mut self, board: &Board) -> Piece {
+        let result = self.deepen(board, |alphabeta, depth| alphabeta.search_gift(board, depth).map(|(piece, _)| piece));
+        info!("Piece searched until depth = {}", self.depth);
+
+        result.unwrap_or_else(|| *board.get_available_pieces().values().next().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::ai::budget::tests::FakeClock;
+    use crate::board::Cell;
+
+    fn mid_game_board() -> Board {
+        let mut board = Board::create();
+        for (piece, cell) in [("DFXC", 0), ("DETS", 1), ("WFTC", 6), ("DFTC", 7), ("WEXS", 9)] {
+            board
+                .play_and_remove_piece(&Move::new(Piece::from(piece), Cell::new(cell).unwrap()))
+                .unwrap();
+        }
+        board
+    }
+
+    #[test]
+    fn test_same_move_as_alphabeta_at_the_reached_depth() {
+        let board = mid_game_board();
+        let piece = *board.get_available_pieces().values().next().unwrap();
+
+        let mut deepening = IterativeDeepening::with_clock(Budget::nodes(20_000), Arc::new(FakeClock::default()));
+        let selected_move = deepening.calc_move(&board, Some(piece)).unwrap();
+        assert!(deepening.depth() >= 2);
+
+        let mut alphabeta = AlphaBeta::new(deepening.depth());
+        assert_eq!(alphabeta.calc_move(&board, Some(piece)).unwrap(), selected_move);
+    }
+
+    #[test]
+    fn test_deeper_with_more_budget() {
+        let board = mid_game_board();
+
+        let mut small = IterativeDeepening::with_clock(Budget::nodes(1_000), Arc::new(FakeClock::default()));
+        let mut large = IterativeDeepening::with_clock(Budget::nodes(50_000), Arc::new(FakeClock::default()));
+        small.calc_move(&board, None).unwrap();
+        large.calc_move(&board, None).unwrap();
+        assert!(small.depth() < large.depth());
+
+        // The search at depth 1 always finishes
+        let mut empty = IterativeDeepening::with_clock(Budget::nodes(0), Arc::new(FakeClock::default()));
+        assert!(empty.calc_move(&board, None).is_ok());
+        assert_eq!(empty.depth(), 1);
+        empty.choose_piece_for_opponent(&board);
+        assert_eq!(empty.depth(), 1);
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let board = mid_game_board();
+
+        // The fake clock moves of one millisecond every 256 positions
+        let mut deepening =
+            IterativeDeepening::with_clock(Budget::time(Duration::from_millis(10)), Arc::new(FakeClock::default()));
+        deepening.calc_move(&board, None).unwrap();
+        assert!(deepening.nodes() < 20 * 256);
+
+        // Near the end of the game, the whole game is searched before the budget is spent
+        let mut end_game = IterativeDeepening::new(Budget::time(Duration::from_secs(60)));
+        let mut board = board;
+        for (piece, cell) in [("WETC", 10), ("DEXS", 12), ("WFXS", 13), ("DEXC", 14), ("WFTS", 15)] {
+            board
+                .play_and_remove_piece(&Move::new(Piece::from(piece), Cell::new(cell).unwrap()))
+                .unwrap();
+        }
+        end_game.calc_move(&board, None).unwrap();
+        assert_eq!(end_game.depth(), board.get_empty_cells().len());
+    }
+}
@@ -0,0 +1,129 @@
+//! # Evaluation of the positions
+//! The minmax strategies stop at a depth and score the boards there with an `Evaluator`.
+//! Each evaluator is a different guess of who is doing well, they can be compared by letting them play together.
+
+use std::fmt::Debug;
+
+use crate::ai::Board;
+use crate::ai::Piece;
+use crate::ai::Score;
+
+/// Score a board at the end of a search, for the player to move
+pub trait Evaluator: Clone + Debug + Send + Sync {
+    fn name(&self) -> String;
+
+    fn evaluate(&self, board: &Board) -> Score;
+
+    /// Score the turn of the player who has to place the piece, when the search stops there.
+    /// A piece which completes a line is a win in one placement, whatever the heuristic says
+    fn evaluate_turn(&self, board: &Board, piece: Option<Piece>) -> Score {
+        if let Some(score) = Score::game_over(board) {
+            return score;
+        }
+        match piece {
+            Some(piece) if !board.rules().misere() && board.winning_pieces_mask() & (1 << piece.number()) != 0 => {
+                Score::Win(1)
+            }
+            _ => self.evaluate(board),
+        }
+    }
+}
+
+/// The historic heuristic: on each line, 2 pieces sharing an attribute value give 1 point, 3 pieces give 2 points.
+/// The alignments are the chances of a Quarto for the player to move
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ClassicEvaluator;
+
+impl Evaluator for ClassicEvaluator {
+    fn name(&self) -> String {
+        String::from("Classic")
+    }
+
+    fn evaluate(&self, board: &Board) -> Score {
+        Score::calc_score(board)
+    }
+}
+
+/// Only the lines one piece away from a Quarto count: each of them gives 1 point,
+/// and 1 more point for each piece left which could complete it.
+/// The misère rules are scored like the classic heuristic, a live line is already what it avoids
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LiveLinesEvaluator;
+
+impl Evaluator for LiveLinesEvaluator {
+    fn name(&self) -> String {
+        String::from("LiveLines")
+    }
+
+    fn evaluate(&self, board: &Board) -> Score {
+        if board.rules().misere() {
+            return Score::calc_score(board);
+        }
+        if let Some(score) = Score::game_over(board) {
+            return score;
+        }
+
+        let points: usize = board
+            .lines()
+            .iter()
+            .filter(|line| (board.occupied_mask() & *line).count_ones() == line.count_ones() - 1)
+            .map(|line| Score::completing_pieces(board, *line))
+            .filter(|pieces| *pieces != 0)
+            .map(|pieces| 1 + (pieces & board.available_mask()).count_ones() as usize)
+            .sum();
+
+        Score::Point(points as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Cell;
+    use crate::piece::Piece;
+    use crate::r#move::Move;
+    use crate::rules::Rules;
+
+    fn board_from(moves: &[(&str, usize)], rules: Rules) -> Board {
+        let mut board = Board::create_with_rules(rules);
+        for (piece, cell) in moves {
+            board
+                .play_and_remove_piece(&Move::new(Piece::from(*piece), Cell::new(*cell).unwrap()))
+                .unwrap();
+        }
+        board
+    }
+
+    #[test]
+    fn test_classic_evaluator_is_the_score() {
+        let board = board_from(&[("WETS", 0), ("DFTC", 1), ("DFTS", 2)], Rules::classic());
+        assert_eq!(ClassicEvaluator.evaluate(&board), Score::calc_score(&board));
+        assert_eq!(ClassicEvaluator.evaluate(&board), Score::Point(5));
+    }
+
+    #[test]
+    fn test_live_lines_evaluator() {
+        // 2 pieces on a line is not a live line yet
+        let board = board_from(&[("DFTC", 0), ("DFTS", 1)], Rules::classic());
+        assert_eq!(LiveLinesEvaluator.evaluate(&board), Score::Point(0));
+
+        // The first row only misses a piece, the 9 dark or flat pieces left complete it
+        let board = board_from(&[("DFTC", 0), ("DFTS", 1), ("DFXS", 2)], Rules::classic());
+        assert_eq!(LiveLinesEvaluator.evaluate(&board), Score::Point(1 + 9));
+
+        // Nothing in common, the line is dead
+        let board = board_from(&[("DFTC", 0), ("WEXS", 1), ("DFXS", 2)], Rules::classic());
+        assert_eq!(LiveLinesEvaluator.evaluate(&board), Score::Point(0));
+
+        let board = board_from(&[("DFTC", 0), ("DFTS", 1), ("DFXS", 2), ("DEXC", 3)], Rules::classic());
+        assert_eq!(LiveLinesEvaluator.evaluate(&board), Score::Loss(0));
+    }
+
+    #[test]
+    fn test_evaluate_turn() {
+        let board = board_from(&[("DFTC", 0), ("DFTS", 1), ("DFXS", 2)], Rules::classic());
+        assert_eq!(ClassicEvaluator.evaluate_turn(&board, Some(Piece::from("DEXC"))), Score::Win(1));
+        assert_eq!(ClassicEvaluator.evaluate_turn(&board, Some(Piece::from("WEXC"))), ClassicEvaluator.evaluate(&board));
+        assert_eq!(ClassicEvaluator.evaluate_turn(&board, None), ClassicEvaluator.evaluate(&board));
+    }
+}
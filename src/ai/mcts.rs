@@ -0,0 +1,354 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::ai::Board;
+use crate::ai::ErrorGame;
+use crate::ai::Piece;
+use crate::ai::Strategy;
+use crate::r#move::Move;
+
+/// The exploration constant of UCT, the one of the theory for rewards between 0 and 1
+pub const DEFAULT_EXPLORATION: f64 = std::f64::consts::SQRT_2;
+
+/// The reward of a draw, a win is 1 and a loss 0
+const DRAW: f64 = 0.5;
+
+/// A half of a turn: a turn of Quarto is the placement of the piece received, then the gift of a piece to the opponent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Place(Move),
+    Give(Piece),
+}
+
+/// What is expected after an action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    /// Place this piece (any piece when none is given)
+    Place(Option<Piece>),
+    Give,
+}
+
+#[derive(Debug)]
+struct Node {
+    /// The action which leads to this node (none for the root)
+    action: Option<Action>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /// The actions which don't have their node yet
+    untried: Vec<Action>,
+    /// The player who did the action: 0 is the player who searches
+    player: usize,
+    visits: u32,
+    /// The sum of the rewards of the player who did the action
+    reward: f64,
+    /// The reward of the player who did the action when it has ended the game
+    outcome: Option<f64>,
+}
+
+impl Node {
+    /// The player who plays after this node
+    fn next_player(&self) -> usize {
+        match self.action {
+            // After the placement, the same player gives a piece
+            Some(Action::Place(_)) => self.player,
+            _ => 1 - self.player,
+        }
+    }
+
+    /// What is expected after this node (the root step is given by the search)
+    fn next_step(&self, root_step: Step) -> Step {
+        match self.action {
+            None => root_step,
+            Some(Action::Place(_)) => Step::Give,
+            Some(Action::Give(piece)) => Step::Place(Some(piece)),
+        }
+    }
+}
+
+/// Monte Carlo Tree Search with the UCT formula. It doesn't use `Score`:
+/// each iteration goes down the tree to a new action, then plays the game randomly until the end,
+/// and the result (win, loss or draw) goes up to the root.
+/// The actions are the placements and the gifts of the pieces, each one is a node of the tree
+#[derive(Debug, Clone)]
+pub struct Mcts {
+    iterations: usize,
+    exploration: f64,
+    rng: StdRng,
+}
+
+impl Mcts {
+    pub fn name() -> String {
+        String::from("MCTS")
+    }
+
+    /// A search with this number of iterations, with a random seed
+    pub fn new(iterations: usize) -> Mcts {
+        Mcts {
+            iterations,
+            exploration: DEFAULT_EXPLORATION,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// The higher it is, the more the less visited actions are tried
+    pub fn with_exploration(mut self, exploration: f64) -> Mcts {
+        self.exploration = exploration;
+        self
+    }
+
+    /// Always play the same way with the same seed
+    pub fn with_seed(mut self, seed: u64) -> Mcts {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    fn actions(board: &Board, step: Step) -> Vec<Action> {
+        match step {
+            Step::Place(Some(piece)) => board
+                .get_available_moves_from_piece(piece)
+                .into_iter()
+                .map(Action::Place)
+                .collect(),
+            Step::Place(None) => board.get_available_moves().into_iter().map(Action::Place).collect(),
+            Step::Give => board.get_available_pieces().into_values().map(Action::Give).collect(),
+        }
+    }
+
+    /// Do the action, and return the reward of the player who did it if the game is over
+    fn apply(board: &mut Board, action: Action) -> Option<f64> {
+        match action {
+            Action::Place(m) => {
+                board.play_and_remove_piece(&m).unwrap();
+                if !board.completed_lines(m.cell().to_index()).is_empty() {
+                    // Completing a line loses with the misère rules
+                    Some(if board.rules().misere() { 0.0 } else { 1.0 })
+                } else if !board.can_play_another_turn() {
+                    Some(DRAW)
+                } else {
+                    None
+                }
+            }
+            Action::Give(_) => None,
+        }
+    }
+
+    /// Play randomly until the end of the game, and return the reward of the player 0
+    fn playout(&mut self, board: &mut Board, mut step: Step, mut player: usize) -> f64 {
+        loop {
+            let Some(action) = Mcts::actions(board, step).choose(&mut self.rng).copied() else {
+                return DRAW;
+            };
+
+            if let Some(reward) = Mcts::apply(board, action) {
+                return if player == 0 { reward } else { 1.0 - reward };
+            }
+            if let Action::Give(piece) = action {
+                step = Step::Place(Some(piece));
+                player = 1 - player;
+            } else {
+                step = Step::Give;
+            }
+        }
+    }
+
+    /// The child with the best UCT value
+    fn select_child(&self, nodes: &[Node], node: usize) -> usize {
+        let log_visits = (nodes[node].visits as f64).ln();
+        let uct = |child: &Node| {
+            child.reward / child.visits as f64 + self.exploration * (log_visits / child.visits as f64).sqrt()
+        };
+
+        nodes[node]
+            .children
+            .iter()
+            .copied()
+            .max_by(|a, b| uct(&nodes[*a]).total_cmp(&uct(&nodes[*b])))
+            .unwrap()
+    }
+
+    /// Build the tree from the board, and return the action of the root which has been the most visited
+    fn search(&mut self, board: &Board, root_step: Step) -> Option<Action> {
+        let mut nodes = vec![Node {
+            action: None,
+            parent: None,
+            children: vec![],
+            untried: Mcts::actions(board, root_step),
+            player: 1,
+            visits: 0,
+            reward: 0.0,
+            outcome: None,
+        }];
+
+        for _ in 0..self.iterations {
+            let mut board = board.clone();
+            let mut node = 0;
+
+            // Selection: go down while every action of the node has been tried
+            while nodes[node].outcome.is_none() && nodes[node].untried.is_empty() && !nodes[node].children.is_empty() {
+                node = self.select_child(&nodes, node);
+                Mcts::apply(&mut board, nodes[node].action.unwrap());
+            }
+
+            // Expansion: add one of the actions not tried yet
+            if nodes[node].outcome.is_none() && !nodes[node].untried.is_empty() {
+                let index = self.rng.gen_range(0..nodes[node].untried.len());
+                let action = nodes[node].untried.swap_remove(index);
+                let outcome = Mcts::apply(&mut board, action);
+
+                let mut child = Node {
+                    action: Some(action),
+                    parent: Some(node),
+                    children: vec![],
+                    untried: vec![],
+                    player: nodes[node].next_player(),
+                    visits: 0,
+                    reward: 0.0,
+                    outcome,
+                };
+                if outcome.is_none() {
+                    child.untried = Mcts::actions(&board, child.next_step(root_step));
+                }
+                nodes.push(child);
+                let child = nodes.len() - 1;
+                nodes[node].children.push(child);
+                node = child;
+            }
+
+            // Simulation: the reward of the player 0
+            let reward = match nodes[node].outcome {
+                Some(reward) if nodes[node].player == 0 => reward,
+                Some(reward) => 1.0 - reward,
+                None => {
+                    let (step, player) = (nodes[node].next_step(root_step), nodes[node].next_player());
+                    self.playout(&mut board, step, player)
+                }
+            };
+
+            // Backpropagation
+            let mut current = Some(node);
+            while let Some(index) = current {
+                let node = &mut nodes[index];
+                node.visits += 1;
+                node.reward += if node.player == 0 { reward } else { 1.0 - reward };
+                current = node.parent;
+            }
+        }
+
+        // The most visited action is the most reliable one
+        nodes[0]
+            .children
+            .iter()
+            .map(|child| &nodes[*child])
+            .max_by_key(|child| child.visits)
+            .and_then(|child| child.action)
+            .or_else(|| nodes[0].untried.first().copied())
+    }
+}
+
+impl Strategy for Mcts {
+    fn name(&self) -> String {
+        Mcts::name()
+    }
+
+    fn calc_move(&mut self, board: &Board, piece: Option<Piece>) -> Result<Move, ErrorGame> {
+        match self.search(board, Step::Place(piece)) {
+            Some(Action::Place(m)) => Ok(m),
+            _ => Err(ErrorGame::NoBestMove {
+                depth: 0,
+                position: board.to_notation(),
+            }),
+        }
+    }
+
+    fn choose_piece_for_opponent(&mut self, board: &Board) -> Piece {
+        match self.search(board, Step::Give) {
+            Some(Action::Give(piece)) => piece,
+            _ => *board.get_available_pieces().values().next().unwrap(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::random::RandomAI;
+    use crate::board::Cell;
+    use crate::rules::Rules;
+
+    fn board_from(moves: &[(&str, usize)], rules: Rules) -> Board {
+        let mut board = Board::create_with_rules(rules);
+        for (piece, cell) in moves {
+            board
+                .play_and_remove_piece(&Move::new(Piece::from(*piece), Cell::new(*cell).unwrap()))
+                .unwrap();
+        }
+        board
+    }
+
+    #[test]
+    fn test_play_the_winning_move() {
+        let board = board_from(&[("DEXC", 0), ("DFXC", 1), ("DETC", 2), ("WFTS", 5)], Rules::classic());
+
+        let mut mcts = Mcts::new(2_000).with_seed(1);
+        let selected_move = mcts.calc_move(&board, Some(Piece::from("DFTC"))).unwrap();
+        assert_eq!(selected_move.cell().to_index(), 3);
+    }
+
+    #[test]
+    fn test_avoid_completing_a_line_with_misere_rules() {
+        let board = board_from(&[("DEXC", 0), ("DFXC", 1), ("DETC", 2)], Rules::classic().with_misere(true));
+
+        let mut mcts = Mcts::new(2_000).with_seed(1);
+        let selected_move = mcts.calc_move(&board, Some(Piece::from("DFTC"))).unwrap();
+        assert_ne!(selected_move.cell().to_index(), 3);
+    }
+
+    #[test]
+    fn test_dont_give_a_winning_piece() {
+        let board = board_from(&[("DEXC", 0), ("DFXC", 1), ("DETC", 2), ("WFTS", 5)], Rules::classic());
+
+        let mut mcts = Mcts::new(3_000).with_seed(1);
+        let piece = mcts.choose_piece_for_opponent(&board);
+        assert_eq!(board.winning_pieces_mask() & (1 << piece.number()), 0);
+    }
+
+    #[test]
+    fn test_stronger_than_random() {
+        let mut wins = 0;
+        for game in 0..10 {
+            let mut players: [Box<dyn Strategy>; 2] = [Box::new(Mcts::new(300).with_seed(game)), Box::new(RandomAI::new().with_seed(game))];
+            let mut board = Board::create();
+            // The MCTS places first in half of the games
+            let mut placer = (game % 2) as usize;
+            loop {
+                let piece = players[1 - placer].choose_piece_for_opponent(&board);
+                let m = players[placer].calc_move(&board, Some(piece)).unwrap();
+                board.play_and_remove_piece(&m).unwrap();
+                if !board.completed_lines(m.cell().to_index()).is_empty() {
+                    wins += (placer == 0) as usize;
+                    break;
+                }
+                if !board.can_play_another_turn() {
+                    break;
+                }
+                placer = 1 - placer;
+            }
+        }
+        assert!(wins >= 7, "{} wins", wins);
+    }
+
+    #[test]
+    fn test_same_seed_same_choices() {
+        let board = board_from(&[("DEXC", 5), ("WFTS", 10)], Rules::classic());
+
+        let mut first = Mcts::new(500).with_seed(42).with_exploration(1.0);
+        let mut second = Mcts::new(500).with_seed(42).with_exploration(1.0);
+        assert_eq!(first.choose_piece_for_opponent(&board), second.choose_piece_for_opponent(&board));
+        assert_eq!(first.calc_move(&board, None).unwrap(), second.calc_move(&board, None).unwrap());
+    }
+}
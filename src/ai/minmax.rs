@@ -1,11 +1,8 @@
+use crate::ai::evaluator::{ClassicEvaluator, Evaluator};
 use crate::board::Board;
-use crate::board::BoardIndex;
 use crate::error::ErrorGame;
 use crate::piece::Piece;
 use crate::r#move::Move;
-use std::cmp::max;
-use std::cmp::min;
-use std::collections::HashMap;
 
 use super::get_moves;
 use super::Score;
@@ -14,24 +11,52 @@ use super::Strategy;
 /// This minmax algorithm is no longer in use
 /// This is the minmax_tree currently use as the ai because I store every move as a tree, it's easier to
 /// store value and to debug
-
-
 // #[deprecated = "Please use MinMaxTree implementation instead"]
 /// The MinMax struct
-pub struct MinMax {
+pub struct MinMax<E: Evaluator = ClassicEvaluator> {
     pub depth: usize,
-    pub maximise: bool,
+    /// Score the positions at the end of the search
+    evaluator: E,
 }
 
 impl MinMax {
-    pub fn new(depth: usize, maximise: bool) -> MinMax {
-        MinMax { depth, maximise }
+    pub fn new(depth: usize) -> MinMax {
+        MinMax::with_evaluator(depth, ClassicEvaluator)
     }
 
     pub fn name() -> String {
         String::from("minmax")
     }
 
+    /// The combinaison (nb_piece_already_played, depth to search)
+    /// For example here:
+    ///     - between 0 and 5 pieces, we search with depth = 2
+    ///     - between 5 and 8 pieces, we search with depth = 3
+    ///     - etc
+    /// I try this to have a better algo in end game
+    pub fn calc_adequat_depth(nb_piece_left: usize) -> usize {
+        // const THRESHOLD_PIECE_PLAYED_DEPTH: Vec<((usize, usize), usize)> = vec![((0, 5), 2), ((5, 8), 3), ((8, 11), 4), ((11, 16), 5)];
+        // let max_cells = WIDTH_BOARD * HEIGHT_BOARD - 1;
+        match nb_piece_left {
+            0..=4 => 2,
+            5..=7 => 3,
+            8..=10 => 4,
+            11..=15 => 5,
+            _ => 0
+        }
+    }
+}
+
+impl<E: Evaluator> MinMax<E> {
+    /// A MinMax which scores the positions with the evaluator
+    pub fn with_evaluator(depth: usize, evaluator: E) -> MinMax<E> {
+        MinMax { depth, evaluator }
+    }
+
+    pub fn evaluator(&self) -> &E {
+        &self.evaluator
+    }
+
     pub fn depth(&self) -> usize {
         self.depth
     }
@@ -40,10 +65,11 @@ impl MinMax {
         self.depth = depth;
     }
 
-    /// Basic MinMax algorithm with just tracking score of each node
-    pub fn minmax(board: &Board, depth: usize, maximise: bool, available_moves: &Vec<Move>) -> Score {
-        if depth == 0 || !board.can_play_another_turn() {
-            let score = Score::calc_score(board);
+    /// Basic MinMax algorithm with just tracking score of each node:
+    /// the score of the turn for the player who places the piece, searched for `depth` placements
+    pub fn minmax(&self, board: &Board, depth: usize, piece: Piece) -> Score {
+        if depth == 0 || Score::game_over(board).is_some() {
+            let score = self.evaluator.evaluate_turn(board, Some(piece));
             debug!("MinMax depth = 0, score = {:?}", score);
 
             return score;
@@ -51,136 +77,121 @@ impl MinMax {
 
         trace!("Current MinMax depth = {}", depth);
 
-        let mut score;
-        if maximise {
-            score = Score::Point(usize::MIN);
-            for m in available_moves {
-                let mut board = board.clone();
-                board.play_and_remove_piece(&m).unwrap();
-
-                let save_old_score = score;
-                score = max(
-                    score,
-                    MinMax::minmax(&board, depth - 1, !maximise, &board.get_available_moves()),
-                );
-                if score != save_old_score {
-                    debug!("From move ({:?}) : maximising / depth = {} / old score = {:?} / minmax score = {:?} ", m, depth, save_old_score, score);
-                }
-            }
-        } else {
-            score = Score::Win;
-            for m in available_moves {
-                let mut board = board.clone();
-                board.play_and_remove_piece(&m).unwrap();
-
-                let save_old_score = score;
-                score = min(
-                    score,
-                    MinMax::minmax(&board, depth - 1, !maximise, &board.get_available_moves()),
-                );
-
-                if score != save_old_score {
-                    debug!("From move ({:?}) : minimising / depth = {} / old score = {:?} / minmax score = {:?}", m, depth, save_old_score, score);
-                }
-            }
+        board
+            .get_available_moves_from_piece(piece)
+            .iter()
+            .map(|m| self.placement(board, m, depth))
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// The score of the placement for the player who plays it:
+    /// the game is over, or the player gives the piece which is the worst for the opponent
+    pub fn placement(&self, board: &Board, m: &Move, depth: usize) -> Score {
+        let mut board = board.clone();
+        board.play_and_remove_piece(m).unwrap();
+
+        if let Some(score) = Score::game_over(&board) {
+            return score.for_previous_player();
         }
+
+        let score = self.gift(&board, depth.saturating_sub(1));
+        debug!("From move ({:?}) / depth = {} / score = {:?}", m, depth, score);
         score
     }
 
+    /// The score of the player who has just placed a piece, when the best piece is given to the opponent.
+    /// The distances count the placement already played
+    pub fn gift(&self, board: &Board, depth: usize) -> Score {
+        board
+            .get_available_pieces()
+            .into_values()
+            .map(|piece| self.minmax(board, depth, piece).for_previous_player())
+            .max()
+            .unwrap_or_default()
+    }
+
     /// Calc the score for each move (wrapper of minmax function)
-    fn calc_next_moves_score(
-        board: &Board,
-        depth: usize,
-        maximise: bool,
-        piece: Option<Piece>,
-    ) -> Vec<(Score, Move)> {
+    fn calc_next_moves_score(&self, board: &Board, depth: usize, piece: Option<Piece>) -> Vec<(Score, Move)> {
         let moves = get_moves(board, piece);
-        let mut move_result: Vec<(Score, Move)> = vec![];
-
-        for m in moves {
-            let score = MinMax::minmax(&board.clone(), depth, maximise, &vec![m.clone()]);
+        let score_move = |m: Move| {
+            let score = self.placement(board, &m, depth);
             trace!("Play move = {} / Score {:?}", &m, &score);
 
-            move_result.push((score, m));
+            (score, m)
+        };
+
+        // The moves are independent, with the `parallel` feature they are searched on several threads
+        // (the results stay in the order of the moves)
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "parallel")] {
+                use rayon::prelude::*;
+                moves.into_par_iter().map(score_move).collect()
+            } else {
+                moves.into_iter().map(score_move).collect()
+            }
         }
-
-        move_result
     }
 
-    /// The combinaison (nb_piece_already_played, depth to search)
-    /// For example here:
-    ///     - between 0 and 5 pieces, we search with depth = 2
-    ///     - between 5 and 8 pieces, we search with depth = 3
-    ///     - etc
-    /// I try this to have a better algo in end game
-    pub fn calc_adequat_depth(nb_piece_left: usize) -> usize {
-        // const THRESHOLD_PIECE_PLAYED_DEPTH: Vec<((usize, usize), usize)> = vec![((0, 5), 2), ((5, 8), 3), ((8, 11), 4), ((11, 16), 5)];
-        // let max_cells = WIDTH_BOARD * HEIGHT_BOARD - 1;
-        match nb_piece_left {
-            0..=4 => 2,
-            5..=7 => 3,
-            8..=10 => 4,
-            11..=15 => 5,
-            _ => 0
+    /// Calc the score of each piece given to the opponent, the score of the opponent turn taken from our side
+    fn calc_gifts_score(&self, board: &Board, depth: usize) -> Vec<(Score, Piece)> {
+        let pieces: Vec<Piece> = board.get_available_pieces().into_values().collect();
+        let score_gift = |piece: Piece| (-self.minmax(board, depth, piece), piece);
+
+        // Like the moves, the gifts are searched on several threads with the `parallel` feature
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "parallel")] {
+                use rayon::prelude::*;
+                pieces.into_par_iter().map(score_gift).collect()
+            } else {
+                pieces.into_iter().map(score_gift).collect()
+            }
         }
     }
+
 }
 
-impl Strategy for MinMax {
+impl<E: Evaluator> Strategy for MinMax<E> {
     fn name(&self) -> String {
         MinMax::name()
     }
 
     fn calc_move(&mut self, board: &Board, piece: Option<Piece>) -> Result<Move, ErrorGame> {
-        let moves_score_result =
-            MinMax::calc_next_moves_score(board, self.depth, self.maximise, piece);
+        let no_best_move = || ErrorGame::NoBestMove {
+            depth: self.depth,
+            position: board.to_notation(),
+        };
+        let moves_score_result = self.calc_next_moves_score(board, self.depth, piece);
         debug!("calc_move >> moves_score_result = {:?}", moves_score_result);
 
-        Ok(if self.maximise {
-            //If maximise, we take take the max score
-            let res = moves_score_result
-                .into_iter()
-                .max_by_key(|s| s.0)
-                .ok_or(ErrorGame::NoBestMove)?;
-            info!(
-                "The max score selected is : {:?} for the move : {}",
-                &res.0, &res.1
-            );
-            res.1
-        } else {
-            // If minimise we take the min
-            let res = moves_score_result
-                .into_iter()
-                .min_by_key(|s| s.0)
-                .ok_or(ErrorGame::NoBestMove)?;
-            info!(
-                "The min score selected is : {:?} for the move : {}",
-                &res.0, &res.1
-            );
-            res.1
-        })
+        // We take the max score, the last one when several moves have it
+        let res = moves_score_result
+            .into_iter()
+            .max_by_key(|s| s.0)
+            .ok_or_else(no_best_move)?;
+        info!(
+            "The max score selected is : {:?} for the move : {}",
+            &res.0, &res.1
+        );
+        Ok(res.1)
     }
 
+    /// The piece choice is a part of the minmax: the best piece for us is the worst for the opponent
     fn choose_piece_for_opponent(&mut self, board: &Board) -> Piece {
-        let moves_score_result = MinMax::calc_next_moves_score(board, self.depth, true, None);
-
-        let mut best_move_per_piece: HashMap<usize, Score> = HashMap::new();
-        for (new_score, new_move) in moves_score_result {
-            best_move_per_piece
-                .entry(new_move.piece().to_index(&board).unwrap())
-                .and_modify(|score| *score = max(*score, new_score))
-                .or_insert(new_score);
-        }
-        info!("best_move_per_piece = {:?}", best_move_per_piece);
-        let worst_score = best_move_per_piece.into_iter().min_by_key(|k| k.1).unwrap();
-        Piece::from_index(&board, worst_score.0).unwrap()
+        // The last piece when several have the max score
+        let (score, piece) = self.calc_gifts_score(board, self.depth)
+            .into_iter()
+            .max_by_key(|s| s.0)
+            .unwrap();
+        info!("The max score selected is : {:?} for the piece : {}", score, piece);
+        piece
     }
 }
 
 #[cfg(test)]
 mod tests {
 
-    use crate::ai::get_moves;
+    use crate::ai::evaluator::{Evaluator, LiveLinesEvaluator};
     use crate::ai::Score;
     use crate::ai::{MinMax, Strategy};
     use crate::board::{BoardIndex, Cell};
@@ -249,13 +260,8 @@ mod tests {
 
         let piece_to_play = Piece::from_index(&board, 1).unwrap();
 
-        let mut minmax = MinMax::new(3, true);
-        let best_minmax_score = MinMax::minmax(
-            &board,
-            minmax.depth,
-            minmax.maximise,
-            &get_moves(&board, Some(piece_to_play)),
-        );
+        let mut minmax = MinMax::new(3);
+        let best_minmax_score = minmax.minmax(&board, minmax.depth, piece_to_play);
         let best_first_move = minmax.calc_move(&board, Some(piece_to_play)).unwrap();
 
         // We got the best score from minmax and we got the best move, now we check that playing this move give the max score
@@ -272,11 +278,29 @@ mod tests {
     #[test]
     fn test_depth_0_eq_current_board_score() {
         let board = fill_board(5);
-        assert_eq!(
-            Score::calc_score(&board),
-            MinMax::minmax(&board, 0, true, &get_moves(&board, None))
-        );
+        // A piece which completes a line would be a win whatever the board
+        let piece = *board
+            .get_available_pieces()
+            .values()
+            .find(|piece| board.winning_pieces_mask() & (1 << piece.number()) == 0)
+            .unwrap();
+        assert_eq!(Score::calc_score(&board), MinMax::new(0).minmax(&board, 0, piece));
+    }
+
+    #[test]
+    fn test_minmax_scores_with_its_evaluator() {
+        let board = fill_board(5);
+        let piece = *board
+            .get_available_pieces()
+            .values()
+            .find(|piece| board.winning_pieces_mask() & (1 << piece.number()) == 0)
+            .unwrap();
+
+        let minmax = MinMax::with_evaluator(0, LiveLinesEvaluator);
+        assert_eq!(minmax.minmax(&board, 0, piece), LiveLinesEvaluator.evaluate_turn(&board, Some(piece)));
+        assert_eq!(minmax.evaluator().name(), LiveLinesEvaluator.name());
     }
+
     #[test]
     fn test_best_play_should_win_in_one_depth() {
         // The first winning move the algorithm has to find in the next turn (depth = 1)
@@ -303,7 +327,7 @@ mod tests {
             board.remove(piece_current).unwrap();
         }
 
-        let mut minmax = MinMax::new(1, true);
+        let mut minmax = MinMax::new(1);
         let best_first_move = minmax.calc_move(&board, None).unwrap();
         assert_eq!(best_first_move, winning_move);
     }
@@ -314,7 +338,7 @@ mod tests {
 
         let moves: Vec<Move> = vec![
             (Move::new(Piece::from("DFXC"), Cell::from_index(&board, 0).unwrap())),
-            (Move::new(Piece::from("DETS"), Cell::from_index(&board, 0).unwrap())),
+            (Move::new(Piece::from("DETS"), Cell::from_index(&board, 1).unwrap())),
             (Move::new(Piece::from("WFTC"), Cell::from_index(&board, 6).unwrap())),
             (Move::new(Piece::from("DFTC"), Cell::from_index(&board, 7).unwrap())),
             (Move::new(Piece::from("WFXS"), Cell::from_index(&board, 8).unwrap())),
@@ -327,11 +351,29 @@ mod tests {
         }
 
         debug!("{}", board);
-        let mut minmax = MinMax::new(2, true);
+        let mut minmax = MinMax::new(2);
         let ai_piece = minmax.choose_piece_for_opponent(&board);
         debug!("Piece choose = {}", ai_piece);
     }
 
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_gifts_keep_the_same_choice() {
+        let board = fill_board(10);
+        let minmax = MinMax::new(2);
+
+        // The gifts searched one after the other, the last of the best is chosen
+        let sequential: Vec<(Score, Piece)> = board
+            .get_available_pieces()
+            .into_values()
+            .map(|piece| (-minmax.minmax(&board, 2, piece), piece))
+            .collect();
+        let best = sequential.iter().max_by_key(|s| s.0).unwrap().1;
+
+        assert_eq!(minmax.calc_gifts_score(&board, 2), sequential);
+        assert_eq!(MinMax::new(2).choose_piece_for_opponent(&board), best);
+    }
+
     #[test]
     fn test_best_play_from_start() {
         let moves = vec![
@@ -362,7 +404,7 @@ mod tests {
 
         info!("{}", board);
 
-        let mut minmax = MinMax::new(3, true);
+        let mut minmax = MinMax::new(3);
         let best_first_move = minmax.calc_move(&board, Some(Piece::from("DEXS"))).unwrap();
         // let best_first_move = calc_move(&board, 2, true, None).unwrap();
         info!("best move = ({})", best_first_move);
@@ -412,6 +454,5 @@ mod tests {
         // }
         // info!("{:?}", board.get_available_moves());
         // info!("{}", board);
-        assert!(true);
     }
 }
@@ -1,68 +1,70 @@
 use crate::ai::get_moves;
+use crate::ai::evaluator::{ClassicEvaluator, Evaluator};
 use crate::ai::Board;
 use crate::ai::ErrorGame;
 use crate::ai::Piece;
 use crate::ai::Score;
 use crate::ai::Strategy;
-use crate::board::BoardIndex;
+use crate::ai::transposition::{Bound, Table, TableStats, TranspositionTable};
 use crate::r#move::Move;
-use std::cmp::max;
-use std::cmp::min;
 use std::cmp::Ordering;
-use std::collections::HashMap;
 use std::fmt::Debug;
 use std::fmt::Display;
-/// Structure which represent the MinMax algorigthm with score affected to each moves
+
+/// A child of a node: the placement of the turn (none when the node only gives a piece),
+/// then the piece given to the opponent (none when the placement ends the game)
+type Edge = (Option<Move>, Option<Piece>);
+
+/// Structure which represent the MinMax algorigthm with score affected to each moves.
+/// A node is the turn of a player: the placement of the piece received, then the gift of a piece to the opponent.
+/// The scores are the ones of the player of the node, so each node takes the best score of its children
 #[derive(Debug, Eq, PartialEq, Clone)]
-pub struct MinMaxTree {
+pub struct MinMaxTree<E: Evaluator = ClassicEvaluator> {
     /// Piece which had to be played
     piece: Option<Piece>,
-    /// Current move
+    /// The placement which led to this node, or the best placement found for the root
     selected_move: Option<Move>,
-    /// The MinMax score.
+    /// The node is reached by a placement, not only by the gift of a piece (the children of `choose_piece_for_opponent`)
+    placed: bool,
+    /// The MinMax score, for the player who places the piece.
     /// Careful, this is not the score of the current board (except for leaves)
     score: Score,
-    /// Current depth
+    /// Current depth: the number of placements still searched
     depth: usize,
-    /// Do we maximize the score ?
-    maximise: bool,
-    /// The moves available from this board
-    children: Vec<MinMaxTree>,
+    /// The turns of the opponent after each placement and gift
+    children: Vec<MinMaxTree<E>>,
+    /// The scores of the positions already searched, kept between the searches of the root
+    table: TranspositionTable,
+    /// Score the leaves
+    evaluator: E,
 }
 
 /// Implementation of PartialOrd and Ord to allow performing min / max comparison (based on the score)
-impl PartialOrd for MinMaxTree {
+impl<E: Evaluator + Eq> PartialOrd for MinMaxTree<E> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
-impl Ord for MinMaxTree {
+impl<E: Evaluator + Eq> Ord for MinMaxTree<E> {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         self.score.cmp(&other.score)
     }
 }
 
 /// Display the current tree state. also use to display the full tree (ref: to_tree() function)
-impl Display for MinMaxTree {
+impl<E: Evaluator> Display for MinMaxTree<E> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let prelude = if self.depth > 0 {
-            Some(format!(
-                "[{} - {}]",
-                self.depth,
-                if self.maximise {
-                    "Maximising"
-                } else {
-                    "Minimising"
-                }
-            ))
+            Some(format!("[{}]", self.depth))
         } else {
             None
         };
         write!(
             f,
-            "{} {:?} = {}",
+            "{} {:?} then {:?} = {}",
             prelude.unwrap_or("".to_string()),
             self.selected_move,
+            self.piece,
             self.score
         )
     }
@@ -74,59 +76,93 @@ impl MinMaxTree {
     }
 
     /// Create a new MinMaxTree
-    pub fn new(depth: usize, maximise: bool) -> MinMaxTree {
+    pub fn new(depth: usize) -> MinMaxTree {
+        MinMaxTree::with_evaluator(depth, ClassicEvaluator)
+    }
+
+    /// The combinaison (nb_piece_already_played, depth to search)
+    /// For example here:
+    ///     - between 0 and 5 pieces, we search with depth = 2
+    ///     - between 5 and 8 pieces, we search with depth = 3
+    ///     - etc
+    /// I try this to have a better algo in end game
+    pub fn calc_adequat_depth(nb_piece_left: usize) -> usize {
+        match nb_piece_left {
+            0..=7 => 2,
+            8..=10 => 3,
+            11..=15 => 4,
+            _ => 0,
+        }
+    }
+}
+
+impl<E: Evaluator> MinMaxTree<E> {
+    /// Create a new MinMaxTree which scores the leaves with the evaluator
+    pub fn with_evaluator(depth: usize, evaluator: E) -> MinMaxTree<E> {
         MinMaxTree {
             piece: None,
             selected_move: None,
-            score: if maximise {
-                Score::Point(usize::MIN)
-            } else {
-                Score::Win
-            },
-            depth: depth,
-            maximise: maximise,
+            placed: false,
+            score: Score::default(),
+            depth,
             children: vec![],
+            table: TranspositionTable::default(),
+            evaluator,
         }
     }
 
-    /// Create a new MinMaxTree with a predefined move
-    pub fn from_move(c_move: Move, depth: usize, maximise: bool) -> MinMaxTree {
+    /// The turn of the opponent after the edge
+    fn from_edge((selected_move, piece): Edge, depth: usize, evaluator: E) -> MinMaxTree<E> {
         MinMaxTree {
-            piece: None,
-            selected_move: Some(c_move),
-            score: if maximise {
-                Score::Point(usize::MIN)
-            } else {
-                Score::Win
-            },
-            depth: depth,
-            maximise: maximise,
-            children: vec![],
+            piece,
+            selected_move,
+            placed: selected_move.is_some(),
+            ..MinMaxTree::with_evaluator(depth, evaluator)
         }
     }
 
+    pub fn evaluator(&self) -> &E {
+        &self.evaluator
+    }
+
     /// Reset the algo
     fn reset(&mut self) {
-        if self.children.len() > 0 {
-            *self = MinMaxTree::new(self.depth, self.maximise);
+        if !self.children.is_empty() {
+            // The transposition table is still right for the next search, so we keep it
+            let table = std::mem::take(&mut self.table);
+            *self = MinMaxTree::with_evaluator(self.depth, self.evaluator.clone());
+            self.table = table;
         }
     }
 
     #[cfg(test)]
     fn is_leaf(&self) -> bool {
-        self.children.len() == 0
+        self.children.is_empty()
+    }
+
+    /// The score of the child for the player of the parent: one placement earlier,
+    /// unless the parent only gave the piece
+    fn score_for_parent(&self) -> Score {
+        if self.placed {
+            self.score.for_previous_player()
+        } else {
+            -self.score
+        }
+    }
+
+    /// The last child with the best score, from the side of the player of this node
+    fn best_child(&self) -> Option<&MinMaxTree<E>> {
+        self.children
+            .iter()
+            .rev()
+            .find(|child| child.score_for_parent().to_value(0) == self.score.to_value(0))
     }
 
     /// Get the adequat move from immediate children
-    fn update_move_from_first_child(&mut self) {
+    fn update_move_from_best_child(&mut self) {
         if self.selected_move.is_none() {
             trace!("No selected move, we want to find score = {}", self.score);
-            for child in &self.children {
-                trace!("update_move_from_first_child >> child score = {} / child move = {:?} / child depth = {}", child.score, child.selected_move, child.depth);
-                if child.score == self.score {
-                    self.selected_move = child.selected_move;
-                }
-            }
+            self.selected_move = self.best_child().and_then(|child| child.selected_move);
 
             trace!(
                 "I have selected = {:?} with score = {}",
@@ -134,7 +170,7 @@ impl MinMaxTree {
                 self.score
             );
             if self.selected_move.is_none() {
-                error!("update_move_from_first_child : no best selected move have been found !");
+                error!("update_move_from_best_child : no best selected move have been found !");
             }
         }
     }
@@ -144,9 +180,37 @@ impl MinMaxTree {
         get_moves(board, self.piece)
     }
 
+    /// Return the placements available, each one with the pieces which can be given after it
+    fn children_edges(&self, board: &mut Board) -> Vec<Edge> {
+        self.children_moves(board)
+            .into_iter()
+            .flat_map(|m| {
+                board.play_and_remove_piece(&m).unwrap();
+                let gifts: Vec<Option<Piece>> = if Score::game_over(board).is_some() {
+                    vec![None]
+                } else {
+                    board.get_available_pieces().into_values().map(Some).collect()
+                };
+                board.unplay(&m).unwrap();
+                gifts.into_iter().map(move |gift| (Some(m), gift))
+            })
+            .collect()
+    }
+
+    /// Use a transposition table with this number of slots (0 to search without table)
+    pub fn with_table_capacity(mut self, capacity: usize) -> MinMaxTree<E> {
+        self.table = TranspositionTable::new(capacity);
+        self
+    }
+
+    /// How much the transposition table has been used since the creation of the tree
+    pub fn table_stats(&self) -> TableStats {
+        self.table.stats()
+    }
+
     /// Force to calc a move from a specific piece
     #[cfg(test)]
-    fn with_piece(mut self, piece: Piece) -> MinMaxTree {
+    fn with_piece(mut self, piece: Piece) -> MinMaxTree<E> {
         self.piece = Some(piece);
         self
     }
@@ -154,10 +218,77 @@ impl MinMaxTree {
     /// MinMax algorithm with the build of the MinMaxTree tree.
     /// Enable log "debug" if you need informations.
     fn minmax(&mut self, board: &Board) {
+        // The board is cloned once, then each move is played and taken back while searching
+        let mut board = board.clone();
+        board.set_hand(None);
+        let edges = if self.depth == 0 || Score::game_over(&board).is_some() {
+            vec![]
+        } else {
+            self.children_edges(&mut board)
+        };
+        self.search_root(&mut board, edges);
+    }
+
+    /// The minmax of the gift: the children are the turns of the opponent with each piece
+    fn minmax_gift(&mut self, board: &Board) {
+        let mut board = board.clone();
+        board.set_hand(None);
+        let edges = board
+            .get_available_pieces()
+            .into_values()
+            .map(|piece| (None, Some(piece)))
+            .collect();
+        self.search_root(&mut board, edges);
+    }
+
+    /// Search the children of the root, with the same table
+    fn search_root(&mut self, board: &mut Board, edges: Vec<Edge>) {
+        let mut table = std::mem::take(&mut self.table);
+        if edges.is_empty() {
+            self.search(board, &mut table);
+        } else {
+            self.search_children(board, edges, &mut table)
+                .into_iter()
+                .for_each(|child| self.add_child(child));
+            self.update_move_from_best_child();
+        }
+        self.table = table;
+    }
+
+    /// The children of the root are searched one after the other
+    #[cfg(not(feature = "parallel"))]
+    fn search_children(&self, board: &mut Board, edges: Vec<Edge>, table: &mut TranspositionTable) -> Vec<MinMaxTree<E>> {
+        edges
+            .into_iter()
+            .map(|edge| self.search_child(edge, board, table))
+            .collect()
+    }
+
+    /// The children of the root are searched on several threads, with the same table.
+    /// The children are added in the order of the moves, so the choices are the ones of the sequential search
+    #[cfg(feature = "parallel")]
+    fn search_children(&self, board: &mut Board, edges: Vec<Edge>, table: &mut TranspositionTable) -> Vec<MinMaxTree<E>> {
+        use crate::ai::transposition::SharedTable;
+        use rayon::prelude::*;
+
+        let shared = SharedTable::new(std::mem::take(table));
+        let board: &Board = board;
+        let children = edges
+            .into_par_iter()
+            .map(|edge| self.search_child(edge, &mut board.clone(), &mut &shared))
+            .collect();
+        *table = shared.into_inner();
+        children
+    }
+
+    /// Recursive part of the minmax, the board is restored before returning.
+    /// The children already searched from another move order take their score from the table,
+    /// they are then kept without their own children
+    fn search<T: Table>(&mut self, board: &mut Board, table: &mut T) {
         // End the recursivity if we can't go deeper
-        if self.depth == 0 || !board.can_play_another_turn() {
+        if self.depth == 0 || Score::game_over(board).is_some() {
             // We calc the final board score for the leaf
-            self.score = Score::calc_score(board);
+            self.score = self.evaluator.evaluate_turn(board, self.piece);
 
             debug!(
                 "MinMaxTree {:?}, Can play other turn = {}",
@@ -166,62 +297,82 @@ impl MinMaxTree {
             );
         } else {
             // Loop over each child node
-            self.children_moves(&board).into_iter().for_each(|m| {
-                let mut child = MinMaxTree::from_move(m, self.depth - 1, !self.maximise);
-                let previous_score = self.score; // Just for further logging
-
-                // We play the current move
-                let mut board = board.clone();
-                board
-                    .play_and_remove_piece(&child.selected_move.unwrap())
-                    .unwrap();
-
-                // Call minmax recursivity on each children
-                child.minmax(&board);
-
-                // Get the max or min score, depend on the depth of the tree
-                if self.maximise {
-                    self.score = max(&mut *self, &mut child).score;
-                } else {
-                    self.score = min(&mut *self, &mut child).score;
-                }
-
-                debug!(
-                    "{} / Move ({:?}) / depth = {} / Previous score = {:?} / Now score = {:?}",
-                    if self.maximise {
-                        "Maximising"
-                    } else {
-                        "Minimising"
-                    },
-                    child.selected_move.unwrap(),
-                    self.depth,
-                    previous_score,
-                    self.score
-                );
-
-                // Add the child
-                self.children.push(child);
+            self.children_edges(board).into_iter().for_each(|edge| {
+                let child = self.search_child(edge, board, table);
+                self.add_child(child);
             });
 
             debug!(
-                "MinMax depth = {} / maximising = {} / best score = {} / children_tree = {:?}",
-                self.depth, self.maximise, self.score, self.children,
+                "MinMax depth = {} / best score = {} / children_tree = {:?}",
+                self.depth, self.score, self.children,
             );
 
             if self.selected_move.is_none() {
                 // We are here when the minmax has finished, we now need to update the move from children which is equal on the best score
-                self.update_move_from_first_child();
+                self.update_move_from_best_child();
             }
         }
     }
 
+    /// Search the child reached with the placement and the gift
+    fn search_child<T: Table>(&self, edge: Edge, board: &mut Board, table: &mut T) -> MinMaxTree<E> {
+        // A placement is one placement less to search, a gift alone isn't
+        let depth = if edge.0.is_some() { self.depth - 1 } else { self.depth };
+        let mut child = MinMaxTree::from_edge(edge, depth, self.evaluator.clone());
+
+        // We play the current move
+        if let Some(m) = edge.0 {
+            board.play_and_remove_piece(&m).unwrap();
+        }
+
+        // Call minmax recursivity on each children, unless the position is already known.
+        // The leaves are not stored, their score is as fast to calc as to find
+        if child.depth == 0 {
+            child.search(board, table);
+        } else if let Some(entry) = table.probe(board, child.piece, child.depth) {
+            child.score = entry.score();
+        } else {
+            child.search(board, table);
+            table.store(board, child.piece, child.depth, child.score, Bound::Exact);
+        }
+
+        // And take it back
+        if let Some(m) = edge.0 {
+            board.unplay(&m).unwrap();
+        }
+        child
+    }
+
+    /// Keep the score of the child if it's better, then add it.
+    /// The score of the child is the one of the opponent
+    fn add_child(&mut self, child: MinMaxTree<E>) {
+        let previous_score = self.score; // Just for further logging
+
+        let score = child.score_for_parent();
+        if self.children.is_empty() || score.to_value(0) >= self.score.to_value(0) {
+            self.score = score;
+        }
+
+        debug!(
+            "Move ({:?}) then piece ({:?}) / depth = {} / Previous score = {:?} / Now score = {:?}",
+            child.selected_move,
+            child.piece,
+            self.depth,
+            previous_score,
+            self.score
+        );
+
+        // Add the child
+        self.children.push(child);
+    }
+
     /// Display the MinMaxTree as a tree
     #[cfg(test)]
-    fn as_tree(&self, display_leaf: bool) -> termtree::Tree<&MinMaxTree> {
+    fn as_tree(&self, display_leaf: bool) -> termtree::Tree<&MinMaxTree<E>> {
         use termtree::Tree;
 
         let x = self.children.as_slice();
-        let tree: Tree<&MinMaxTree> = x.into_iter().fold(Tree::new(self), |mut root, entry| {
+        let tree: Tree<&MinMaxTree<E>> = x.iter().fold(Tree::new(self), |mut root, entry| {
             if display_leaf {
                 if entry.is_leaf() {
                     root.push(Tree::new(entry));
@@ -229,7 +380,7 @@ impl MinMaxTree {
                     root.push(entry.as_tree(display_leaf));
                 }
             } else {
-                if entry.children.first().unwrap().depth > 0 {
+                if entry.children.first().is_some_and(|child| child.depth > 0) {
                     root.push(entry.as_tree(display_leaf));
                 } else {
                     root.push(Tree::new(entry));
@@ -241,27 +392,17 @@ impl MinMaxTree {
         tree
     }
 
-    /// The combinaison (nb_piece_already_played, depth to search)
-    /// For example here:
-    ///     - between 0 and 5 pieces, we search with depth = 2
-    ///     - between 5 and 8 pieces, we search with depth = 3
-    ///     - etc
-    /// I try this to have a better algo in end game
-    pub fn calc_adequat_depth(nb_piece_left: usize) -> usize {
-        match nb_piece_left {
-            0..=7 => 2,
-            8..=10 => 3,
-            11..=15 => 4,
-            _ => 0,
-        }
-    }
-
     pub fn depth(&self) -> usize {
         self.depth
     }
+
+    /// The score of the selected move (or piece) after a search
+    pub fn score(&self) -> Score {
+        self.score
+    }
 }
 
-impl Strategy for MinMaxTree {
+impl<E: Evaluator> Strategy for MinMaxTree<E> {
     fn name(&self) -> String {
         MinMaxTree::name()
     }
@@ -269,63 +410,31 @@ impl Strategy for MinMaxTree {
     fn calc_move(&mut self, board: &Board, piece: Option<Piece>) -> Result<Move, ErrorGame> {
         self.reset();
 
-        if piece.is_some() {
-            self.piece = Some(piece.unwrap());
+        if let Some(piece) = piece {
+            self.piece = Some(piece);
         }
 
         self.minmax(board);
 
-        let selected_move = self.selected_move.unwrap();
+        let selected_move = self.selected_move.ok_or_else(|| ErrorGame::NoBestMove {
+            depth: self.depth,
+            position: board.to_notation(),
+        })?;
 
         // if we passed a piece in parameter, the move selected by minmax should play this piece
-        if piece.is_some() {
-            assert_eq!(piece.unwrap(), selected_move.piece());
+        if let Some(piece) = piece {
+            assert_eq!(piece, selected_move.piece());
         }
         Ok(selected_move)
     }
 
+    /// The piece is chosen by the same minmax: the best gift is the turn which is the worst for the opponent
     fn choose_piece_for_opponent(&mut self, board: &Board) -> Piece {
-        let mut worst_score: (Option<usize>, Score) = (None, Score::Win);
-        let initial_depth = self.depth;
-
-        while worst_score.1 == Score::Win && self.depth > 0 {
-            self.reset();
-
-            // self.maximise = false;
-            self.minmax(board);
-
-            // Visit of the children to select the worst move
-            let mut best_move_per_piece: HashMap<usize, Score> = HashMap::new();
-            for minmax in &self.children {
-                best_move_per_piece
-                    .entry(
-                        minmax
-                            .selected_move
-                            .unwrap()
-                            .piece()
-                            .to_index(board)
-                            .unwrap(),
-                    )
-                    .and_modify(|score| *score = max(*score, minmax.score))
-                    .or_insert(minmax.score);
-            }
-
-            trace!("best_move_per_piece = {:?}", best_move_per_piece);
-            worst_score = best_move_per_piece
-                .into_iter()
-                .min_by_key(|k| k.1)
-                .map(|k| (Some(k.0), k.1))
-                .unwrap();
-
-            if worst_score.1 == Score::Win {
-                info!("All best move per piece with depth = {} are winning. We decrease depth to find a not winning play", self.depth());
-                self.depth = self.depth - 1;
-            }
-        }
+        self.reset();
+        self.minmax_gift(board);
 
-        self.depth = initial_depth;
-        let piece = Piece::from_index(&board, worst_score.0.unwrap()).unwrap();
-        info!("worst_score = {} which is piece = {}", worst_score.1, piece);
+        let piece = self.best_child().and_then(|child| child.piece).unwrap();
+        info!("best score = {} which is piece = {}", self.score, piece);
         piece
     }
 }
@@ -337,6 +446,7 @@ mod tests {
         board::{Board, BoardIndex, Cell},
         piece::Piece,
         r#move::Move,
+        rules::Rules,
     };
 
     fn late_game(nb_piece_left: usize) -> Vec<Piece> {
@@ -395,7 +505,7 @@ mod tests {
         let piece_to_play = Piece::from_index(&board, 1).unwrap();
         debug!("Should play : {}", &piece_to_play);
         // Tree minmax
-        let mut minmax_tree = MinMaxTree::new(depth, true).with_piece(piece_to_play.clone());
+        let mut minmax_tree = MinMaxTree::new(depth).with_piece(piece_to_play);
         minmax_tree.minmax(&board);
 
         let selected_move = minmax_tree.selected_move.unwrap();
@@ -412,6 +522,61 @@ mod tests {
         debug!("MinMax tree result =  \n{}", minmax_tree.as_tree(true));
     }
 
+    #[test]
+    fn test_transposition_table_keeps_the_same_choices() {
+        let board = fill_board(10);
+        let piece = Piece::from_index(&board, 1).unwrap();
+
+        let mut with_table = MinMaxTree::new(2);
+        let mut without_table = MinMaxTree::new(2).with_table_capacity(0);
+        assert_eq!(
+            with_table.calc_move(&board, Some(piece)).unwrap(),
+            without_table.calc_move(&board, Some(piece)).unwrap()
+        );
+        assert_eq!(with_table.score, without_table.score);
+        let scores = |tree: &MinMaxTree| tree.children.iter().map(|child| child.score).collect::<Vec<Score>>();
+        assert_eq!(scores(&with_table), scores(&without_table));
+
+        // The table is reused to choose the piece, where the same positions are reached from different move orders
+        assert_eq!(
+            with_table.choose_piece_for_opponent(&board),
+            without_table.choose_piece_for_opponent(&board)
+        );
+        assert!(with_table.table_stats().hits > 0);
+        assert!(with_table.table_stats().hit_rate() > 0.0);
+        assert_eq!(without_table.table_stats().hits, 0);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_search_keeps_the_same_choices() {
+        use crate::ai::transposition::TranspositionTable;
+
+        let board = fill_board(10);
+        let piece = Piece::from_index(&board, 1).unwrap();
+
+        for piece in [Some(piece), None] {
+            let mut parallel = MinMaxTree::new(2);
+            parallel.piece = piece;
+            parallel.minmax(&board);
+
+            let mut sequential = MinMaxTree::new(2);
+            sequential.piece = piece;
+            sequential.search(&mut board.clone(), &mut TranspositionTable::default());
+
+            assert_eq!(parallel.selected_move, sequential.selected_move);
+            assert_eq!(parallel.score, sequential.score);
+            let scores = |tree: &MinMaxTree| tree.children.iter().map(|child| (child.selected_move, child.piece, child.score)).collect::<Vec<_>>();
+            assert_eq!(scores(&parallel), scores(&sequential));
+        }
+
+        // The gifts of the root are searched on several threads too
+        let mut parallel = MinMaxTree::new(2);
+        let mut sequential = MinMaxTree::new(2).with_table_capacity(0);
+        assert_eq!(parallel.choose_piece_for_opponent(&board), sequential.choose_piece_for_opponent(&board));
+        assert_eq!(parallel.score, sequential.score);
+    }
+
     #[test]
     fn test_minmax_tree_choose_opponent_piece() {
         let depth: usize = 2;
@@ -420,7 +585,7 @@ mod tests {
         debug!("{}", board);
 
         // Tree minmax
-        let mut minmax_tree = MinMaxTree::new(depth, true);
+        let mut minmax_tree = MinMaxTree::new(depth);
         let piece = minmax_tree.choose_piece_for_opponent(&board);
 
         debug!("Piece choose = {}", piece);
@@ -429,12 +594,44 @@ mod tests {
     #[test]
     fn test_depth_0_eq_current_board_score() {
         let board = fill_board(5);
-        let mut minmax = MinMaxTree::new(0, true);
+        let mut minmax = MinMaxTree::new(0);
         minmax.minmax(&board);
 
         assert_eq!(Score::calc_score(&board), minmax.score);
     }
 
+    #[test]
+    fn test_square_win_with_advanced_rules() {
+        let mut board = Board::create_with_rules(Rules::advanced());
+        board.with_scenario(vec![
+            Move::new(Piece::from("DEXC"), Cell::from_index(&board, 0).unwrap()),
+            Move::new(Piece::from("DFXC"), Cell::from_index(&board, 1).unwrap()),
+            Move::new(Piece::from("DETC"), Cell::from_index(&board, 4).unwrap()),
+        ]);
+
+        let mut algo = MinMaxTree::new(1);
+        let best_move = algo.calc_move(&board, Some(Piece::from("DFTC"))).unwrap();
+
+        assert_eq!(best_move.cell().to_index(), 5);
+        assert_eq!(algo.score, Score::Win(1));
+    }
+
+    #[test]
+    fn test_avoid_line_with_misere_rules() {
+        let mut board = Board::create_with_rules(Rules::classic().with_misere(true));
+        board.with_scenario(vec![
+            Move::new(Piece::from("DEXC"), Cell::from_index(&board, 0).unwrap()),
+            Move::new(Piece::from("DFXC"), Cell::from_index(&board, 1).unwrap()),
+            Move::new(Piece::from("DETC"), Cell::from_index(&board, 2).unwrap()),
+        ]);
+
+        let mut algo = MinMaxTree::new(1);
+        let best_move = algo.calc_move(&board, Some(Piece::from("DFTC"))).unwrap();
+
+        assert_ne!(best_move.cell().to_index(), 3);
+        assert_ne!(algo.score, Score::Loss(1));
+    }
+
     #[test]
     fn test_debug() {
         let mut board = Board::create();
@@ -460,7 +657,7 @@ mod tests {
 
         debug!("{}", board);
 
-        let mut algo = MinMaxTree::new(2, true);
+        let mut algo = MinMaxTree::new(2);
         algo.minmax(&board);
         //info!("{}", algo.as_tree());
         let piece_opponent = algo.choose_piece_for_opponent(&board);
@@ -494,7 +691,7 @@ mod tests {
 
         info!("{}", board);
 
-        let mut algo = MinMaxTree::new(3, true);
+        let mut algo = MinMaxTree::new(3);
         // let best_move = algo.calc_move(&board, Some(Piece::from("DETS")));
         let best_move = algo.calc_move(&board, None);
         info!("best_move = {}", best_move.unwrap());
@@ -512,7 +709,7 @@ mod tests {
     #[test]
     fn bench_minimax() {
         let board = Board::create();
-        let mut algo = MinMaxTree::new(2, true);
+        let mut algo = MinMaxTree::new(2);
 
         let now = std::time::Instant::now();
 
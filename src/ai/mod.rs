@@ -1,38 +1,51 @@
 pub use minmax::MinMax;
 pub use score::Score;
 
+use std::sync::Arc;
+
 use crate::{
-    ai::minmax_tree::MinMaxTree,
+    ai::alphabeta::AlphaBeta,
+    ai::book::{BookStrategy, OpeningBook},
+    ai::budget::{Budget, Clock},
+    ai::deepening::IterativeDeepening,
+    ai::solver::Solver,
     board::{Board, HEIGHT_BOARD, WIDTH_BOARD},
     error::ErrorGame,
     piece::Piece,
     r#move::Move,
 };
 
-use self::random::RandomAI;
-
+pub mod alphabeta;
+pub mod book;
+pub mod budget;
+pub mod deepening;
+pub mod evaluator;
+pub mod mcts;
+pub mod solver;
 mod minmax;
 pub mod minmax_tree;
 pub mod random;
 mod score;
-
-/// The nb move with RandomUI strategy. After this, we will use MinMax algorithm
-const NB_PLAY_WITH_RANDOM_STRATEGY: usize = 2;
+pub mod transposition;
 
 pub trait Strategy {
     fn name(&self) -> String;
     /// Return the best play from :
     /// - the current board state
     /// - the depth of the the search moves
-    /// - Do we currently maximize the score ?
     /// - The piece to be played (it's specific to Quarto)
     fn calc_move(&mut self, board: &Board, piece: Option<Piece>) -> Result<Move, ErrorGame>;
 
     /// Chose the worst piece for the opponent
     fn choose_piece_for_opponent(&mut self, board: &Board) -> Piece;
+
+    /// Return the cells of a line to call "Quarto!" with, if the last move completed one
+    fn claim_quarto(&mut self, board: &Board, last_move: &Move) -> Option<Vec<usize>> {
+        completed_line(board, last_move)
+    }
 }
 
-/// Play the current move (eq to Game struct)
+// Play the current move (eq to Game struct)
 // fn play(board: &mut Board, m: &Move) {
 //     if let Err(e) = board.play(m.piece(), m.cell()) {
 //         error!("{}", e.message());
@@ -41,6 +54,14 @@ pub trait Strategy {
 //     board.remove(m.piece()).unwrap();
 // }
 
+/// The cells of the first line completed by the last move, if any: no search is needed to call "Quarto!"
+pub fn completed_line(board: &Board, last_move: &Move) -> Option<Vec<usize>> {
+    board
+        .completed_lines(last_move.cell().to_index())
+        .first()
+        .map(|line| board.mask_to_btree(*line).into_keys().collect())
+}
+
 /// Return the current available moves from the board
 fn get_moves(board: &Board, piece: Option<Piece>) -> Vec<Move> {
     if let Some(piece) = piece {
@@ -50,20 +71,82 @@ fn get_moves(board: &Board, piece: Option<Piece>) -> Vec<Move> {
     }
 }
 
+/// The kind of strategy chosen for a board. While the kind doesn't change,
+/// the same strategy can go on with the next positions and keep its transposition table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrategyKind {
+    Solver,
+    /// The opening book, then the search
+    Book,
+    /// AlphaBeta with this depth
+    AlphaBeta(usize),
+    /// The depth is the one reached with the budget
+    IterativeDeepening,
+}
+
+/// The kind of `adequat_strategy` for the board, or of `adequat_strategy_with_budget` when the AI has a budget
+pub fn adequat_strategy_kind(board: &Board, with_budget: bool) -> StrategyKind {
+    // Near the end, the game is solved
+    if Solver::new().can_solve(board) {
+        return StrategyKind::Solver;
+    }
+
+    // On the first moves, the answers have already been searched deeper
+    if OpeningBook::embedded().covers(board) {
+        return StrategyKind::Book;
+    }
+
+    if with_budget {
+        StrategyKind::IterativeDeepening
+    } else {
+        StrategyKind::AlphaBeta(AlphaBeta::calc_adequat_depth(nb_piece_left(board)))
+    }
+}
+
+/// The number of pieces already played
+fn nb_piece_left(board: &Board) -> usize {
+    WIDTH_BOARD * HEIGHT_BOARD - board.get_available_pieces().len()
+}
+
 /// Return the adequat AI strategy, depend on board state
 pub fn adequat_strategy(board: &Board) -> Box<dyn Strategy> {
-    // If we are on the first three move, we select RandomAI to play
-    if board.get_empty_cells().len() > (WIDTH_BOARD * HEIGHT_BOARD) - NB_PLAY_WITH_RANDOM_STRATEGY {
-        info!("Current strategy is RandomAI");
-        return Box::new(RandomAI::new());
+    // The search is MinMax algorithm, with alpha-beta pruning to search deeper
+    let alphabeta = AlphaBeta::new(AlphaBeta::calc_adequat_depth(nb_piece_left(board)));
+
+    match adequat_strategy_kind(board, false) {
+        StrategyKind::Solver => {
+            info!("Strategy is Solver");
+            Box::new(Solver::new())
+        }
+        StrategyKind::Book => {
+            info!("Strategy is Book, then AlphaBeta with depth = {}", alphabeta.depth());
+            Box::new(BookStrategy::new(OpeningBook::embedded(), Box::new(alphabeta)))
+        }
+        _ => {
+            info!("Strategy is AlphaBeta with depth = {}", alphabeta.depth());
+            Box::new(alphabeta)
+        }
     }
+}
 
-    // Otherwise we call MinMax algorithm
-    let nb_piece_left = WIDTH_BOARD * HEIGHT_BOARD - board.get_available_pieces().len();
-    let minmax = MinMaxTree::new(MinMaxTree::calc_adequat_depth(nb_piece_left), true);
+/// Like `adequat_strategy`, but the depth of the search is the one reached with the budget
+pub fn adequat_strategy_with_budget(board: &Board, budget: Budget, clock: Arc<dyn Clock>) -> Box<dyn Strategy> {
+    let deepening = IterativeDeepening::with_clock(budget, clock);
 
-    info!("Strategy is MinMaxTree with depth = {}", minmax.depth());
-    return Box::new(minmax);
+    match adequat_strategy_kind(board, true) {
+        StrategyKind::Solver => {
+            info!("Strategy is Solver");
+            Box::new(Solver::new())
+        }
+        StrategyKind::Book => {
+            info!("Strategy is Book, then IterativeDeepening with budget = {:?}", budget);
+            Box::new(BookStrategy::new(OpeningBook::embedded(), Box::new(deepening)))
+        }
+        _ => {
+            info!("Strategy is IterativeDeepening with budget = {:?}", budget);
+            Box::new(deepening)
+        }
+    }
 }
 
 #[cfg(test)]
@@ -75,10 +158,15 @@ mod tests {
     use std::time::Instant;
 
     use crate::ai::adequat_strategy;
-    use crate::ai::minmax_tree::MinMaxTree;
+    use crate::ai::alphabeta::AlphaBeta;
+    use crate::ai::adequat_strategy_with_budget;
+    use crate::ai::book::BookStrategy;
+    use crate::ai::budget::{Budget, SystemClock};
+    use crate::ai::deepening::IterativeDeepening;
+    use crate::ai::solver::Solver;
+    use std::sync::Arc;
     use crate::ai::Board;
     use crate::ai::Piece;
-    use crate::ai::RandomAI;
     use crate::board::BoardState;
     use crate::board::Cell;
     use crate::r#move::Move;
@@ -91,7 +179,7 @@ mod tests {
 
         trace!("Init with strategy = {:?}", strategy.name());
 
-        assert_eq!(strategy.name(), RandomAI::name());
+        assert_eq!(strategy.name(), BookStrategy::name());
     }
 
     #[test]
@@ -100,7 +188,7 @@ mod tests {
 
         let moves: Vec<Move> = vec![
             (Move::new(Piece::from("DFXC"), Cell::from_index(&board, 0).unwrap())),
-            (Move::new(Piece::from("DETS"), Cell::from_index(&board, 0).unwrap())),
+            (Move::new(Piece::from("DETS"), Cell::from_index(&board, 1).unwrap())),
             (Move::new(Piece::from("WFTC"), Cell::from_index(&board, 6).unwrap())),
             (Move::new(Piece::from("DFTC"), Cell::from_index(&board, 7).unwrap())),
         ];
@@ -111,7 +199,39 @@ mod tests {
         let strategy = adequat_strategy(&board);
         trace!("Mid game strategy = {:?}", strategy.name());
 
-        assert_eq!(strategy.name(), MinMaxTree::name());
+        assert_eq!(strategy.name(), AlphaBeta::name());
+    }
+
+    #[test]
+    fn test_end_game_strategy() {
+        let mut board = Board::create();
+        for (index, piece) in ["DFXS", "DFXC", "DFTS", "WEXC", "WFXS", "DFTC", "DEXS", "WEXS"].iter().enumerate() {
+            board
+                .play_and_remove_piece(&Move::new(Piece::from(*piece), Cell::from_index(&board, index * 2).unwrap()))
+                .unwrap();
+        }
+
+        assert_eq!(adequat_strategy(&board).name(), Solver::name());
+        let budget = Budget::time(Duration::from_millis(100));
+        assert_eq!(adequat_strategy_with_budget(&board, budget, Arc::new(SystemClock::new())).name(), Solver::name());
+    }
+
+    #[test]
+    fn test_strategy_with_budget() {
+        let budget = Budget::time(Duration::from_millis(100));
+        let mut board = Board::create();
+        let strategy = adequat_strategy_with_budget(&board, budget, Arc::new(SystemClock::new()));
+        assert_eq!(strategy.name(), BookStrategy::name());
+
+        board.with_scenario(vec![
+            Move::new(Piece::from("DFXC"), Cell::from_index(&board, 0).unwrap()),
+            Move::new(Piece::from("DETS"), Cell::from_index(&board, 1).unwrap()),
+            Move::new(Piece::from("WFTC"), Cell::from_index(&board, 6).unwrap()),
+        ]);
+        let mut strategy = adequat_strategy_with_budget(&board, budget, Arc::new(SystemClock::new()));
+        assert_eq!(strategy.name(), IterativeDeepening::name());
+        let piece = strategy.choose_piece_for_opponent(&board);
+        assert!(strategy.calc_move(&board, Some(piece)).is_ok());
     }
 
     #[test]
@@ -138,7 +258,7 @@ mod tests {
                     MAX_SECOND,
                     elapsed_choose_piece_for_opponent.as_secs()
                 );
-                assert!(false);
+                panic!("choose_piece_for_opponent took too long");
             }
             info!("Round {}, selected_piece for opponent = {}", i, selected_piece);
 
@@ -153,7 +273,7 @@ mod tests {
                     MAX_SECOND,
                     elapsed_calc_move.as_secs()
                 );
-                assert!(false);
+                panic!("calc_move took too long");
             }
             info!("Round {}, best move from piece {} = {}", i, selected_piece, selected_move);
 
@@ -1,25 +1,37 @@
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{seq::SliceRandom, Rng, SeedableRng};
 
 use crate::{board::Board, error::ErrorGame, piece::Piece, r#move::Move};
 
 use super::{get_moves, Strategy};
 
-pub struct RandomAI {}
+pub struct RandomAI {
+    rng: StdRng,
+}
+
+impl Default for RandomAI {
+    fn default() -> Self {
+        Self {
+            rng: StdRng::from_entropy(),
+        }
+    }
+}
 
 impl RandomAI {
     pub fn new() -> RandomAI {
         RandomAI::default()
     }
 
+    /// Always play the same way with the same seed
+    pub fn with_seed(mut self, seed: u64) -> RandomAI {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
     pub fn name() -> String {
         String::from("random")
     }
 }
-impl Default for RandomAI {
-    fn default() -> Self {
-        Self {  }
-    }
-}
 
 /// This strategy is use for the first AI move, because
 impl Strategy for RandomAI {
@@ -32,16 +44,22 @@ impl Strategy for RandomAI {
         board: &Board,
         piece: Option<Piece>,
     ) -> Result<Move, ErrorGame> {
-        let moves = get_moves(&board, piece);
-        Ok(*moves
-            .get(rand::thread_rng().gen_range(0..moves.len()))
-            .ok_or(ErrorGame::NoBestMove)?)
+        let moves = get_moves(board, piece);
+        moves
+            .choose(&mut self.rng)
+            .copied()
+            .ok_or_else(|| ErrorGame::NoBestMove {
+                depth: 0,
+                position: board.to_notation(),
+            })
     }
 
     fn choose_piece_for_opponent(&mut self, board: &Board) -> Piece {
         let pieces = board.get_available_pieces();
+        // The pieces are indexed by their number, so we pick a position rather than a key
         *pieces
-            .get(&rand::thread_rng().gen_range(0..pieces.len() - 1))
+            .values()
+            .nth(self.rng.gen_range(0..pieces.len()))
             .unwrap()
     }
 }
@@ -51,6 +69,9 @@ impl Strategy for RandomAI {
 mod tests {
     use crate::ai::Board;
 use crate::ai::Strategy;
+use crate::board::{BoardIndex, Cell};
+use crate::error::{ErrorGame, ErrorKind};
+use crate::piece::Piece;
 use super::RandomAI;
 
 
@@ -70,4 +91,35 @@ use super::RandomAI;
         RandomAI::new().choose_piece_for_opponent(&board);
         // Just to check nothing panic
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_same_seed_same_choices() {
+        let board = Board::create();
+        let mut first = RandomAI::new().with_seed(42);
+        let mut second = RandomAI::new().with_seed(42);
+        for _ in 0..10 {
+            assert_eq!(first.choose_piece_for_opponent(&board), second.choose_piece_for_opponent(&board));
+            assert_eq!(first.calc_move(&board, None).unwrap(), second.calc_move(&board, None).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_no_move_on_full_board() {
+        let mut board = Board::create();
+        for i in 0..16 {
+            let piece = Piece::from_index(&board, i).unwrap();
+            board.play(piece, Cell::from_index(&board, i).unwrap()).unwrap();
+            board.remove(piece).unwrap();
+        }
+
+        let error = RandomAI::new().calc_move(&board, None).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Ai);
+        assert_eq!(
+            error,
+            ErrorGame::NoBestMove {
+                depth: 0,
+                position: board.to_notation()
+            }
+        );
+    }
+}
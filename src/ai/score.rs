@@ -1,146 +1,171 @@
 use core::cmp::Ordering;
 use std::fmt::Display;
-use std::iter::Sum;
-use crate::board::Board;
-use crate::board::Cell;
-use crate::board::HEIGHT_BOARD;
-use crate::board::WIDTH_BOARD;
-use crate::piece::Color;
-use crate::piece::Height;
-use crate::piece::Hole;
-use crate::piece::Piece;
-use crate::piece::Shape;
+use std::ops::Neg;
+use crate::board::GenericBoard;
+use crate::piece::GenericPiece;
 
+/// The value of a win in the search, minus the number of placements to reach it.
+/// A loss is the opposite, and the heuristic points stay far below
+const WIN: i64 = 1 << 20;
+
+/// The points are doubled in the values, so a draw has its own odd value just below no point
+const DRAW: i64 = -1;
+
+/// The score of a position for the player to move, the one who places the next piece.
+/// The wins and the losses count the placements until the end of the game (the next placement included),
+/// so a quick win is better than a slow one, and a slow loss is better than a quick one
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Score {
-    Point(usize),
-    Win,
+    /// The player to move loses after this number of placements
+    Loss(usize),
+    /// No piece or no cell left without a line
+    Draw,
+    /// The guess of the heuristic, positive when the player to move is doing well
+    Point(i32),
+    /// The player to move wins after this number of placements
+    Win(usize),
 }
 
 impl Score {
-    /// Return the global board score or if the board is winning
-    pub fn calc_score(board: &Board) -> Score {
-        let mut h_score: Vec<Score> = vec![];
-        let mut v_score: Vec<Score> = vec![];
-        let mut d_score_1: Vec<Score> = vec![];
-        let mut d_score_2: Vec<Score> = vec![];
-
-        for i in 0..WIDTH_BOARD {
-            let mut horizontal_cells: Vec<Cell> = vec![];
-            let mut vertical_cells: Vec<Cell> = vec![];
-            for j in 0..HEIGHT_BOARD {
-                horizontal_cells.push(Cell::from_coordinate(&board, j, i).unwrap());
-                vertical_cells.push(Cell::from_coordinate(&board, i, j).unwrap());
-            }
+    /// The score of the board for the player to move: the end of the game if it's over, the heuristic otherwise
+    pub fn calc_score<const W: usize, const H: usize, const A: usize>(board: &GenericBoard<W, H, A>) -> Score {
+        if let Some(score) = Score::game_over(board) {
+            return score;
+        }
+        if board.rules().misere() {
+            return Score::calc_misere_score(board);
+        }
 
-            h_score.push(Score::calc_range_point(&horizontal_cells));
-            v_score.push(Score::calc_range_point(&vertical_cells));
+        Score::Point(
+            board
+                .lines()
+                .iter()
+                .map(|line| Score::calc_line_point(board, *line))
+                .sum(),
+        )
+    }
 
-            if Score::has_win(&h_score) || Score::has_win(&v_score) {
-                return Score::Win;
-            }
+    /// The score of the player to move when the game is over:
+    /// the last placement has completed a line (a win for its player, a loss with the misère rules),
+    /// or there is nothing left to play
+    pub fn game_over<const W: usize, const H: usize, const A: usize>(board: &GenericBoard<W, H, A>) -> Option<Score> {
+        if board.lines().iter().any(|line| board.is_winning_line(*line)) {
+            Some(if board.rules().misere() { Score::Win(0) } else { Score::Loss(0) })
+        } else if !board.can_play_another_turn() {
+            Some(Score::Draw)
+        } else {
+            None
         }
+    }
 
-        let (diagonal_cells_top_left_bottom_right, diagonal_cells_top_right_bottom_left) = Board::get_diagonal_cells(&board);
-        d_score_1.push(Score::calc_range_point(&diagonal_cells_top_left_bottom_right));
-        d_score_2.push(Score::calc_range_point(&diagonal_cells_top_right_bottom_left));
-        if Score::has_win(&d_score_1) || Score::has_win(&d_score_2) {
-            return Score::Win;
+    /// The score of the player who played before, one placement earlier
+    pub fn for_previous_player(self) -> Score {
+        match self {
+            Score::Win(plies) => Score::Loss(plies + 1),
+            Score::Loss(plies) => Score::Win(plies + 1),
+            score => -score,
         }
+    }
 
-        Score::sum_scores(vec![h_score, v_score, d_score_1, d_score_2])
+    /// The score as a number, the wins and losses counted from a position `ply` placements before this one.
+    /// The searches compare these numbers, so the scores of different depths can be compared
+    pub(crate) fn to_value(self, ply: usize) -> i64 {
+        match self {
+            Score::Win(plies) => WIN - (ply + plies) as i64,
+            Score::Loss(plies) => -(WIN - (ply + plies) as i64),
+            Score::Draw => DRAW,
+            Score::Point(point) => 2 * point as i64,
+        }
     }
 
-    /// Add all horizontal / vertical and diagonal score to get a global board score
-    fn sum_scores(scores: Vec<Vec<Score>>) -> Score {
-        scores.into_iter().flatten().sum::<Score>()
+    /// The score of a number given by `to_value` with the same `ply`.
+    /// A win or a loss nearer than `ply` comes from another ply (an old entry of a table for instance):
+    /// it's read as a win or a loss right now rather than stopping the search
+    pub(crate) fn from_value(value: i64, ply: usize) -> Score {
+        let plies = |from_root: i64| (from_root as usize).saturating_sub(ply);
+
+        if value > WIN / 2 {
+            Score::Win(plies(WIN - value))
+        } else if value < -WIN / 2 {
+            Score::Loss(plies(WIN + value))
+        } else if value == DRAW {
+            Score::Draw
+        } else {
+            Score::Point(value.div_euclid(2) as i32)
+        }
     }
 
-    /// Return the score for the current cells
-    fn calc_range_point(cells: &Vec<Cell>) -> Score {
+    /// Return the score for the cells of the line
+    fn calc_line_point<const W: usize, const H: usize, const A: usize>(board: &GenericBoard<W, H, A>, line: u64) -> i32 {
         // We only get pieces which has been already played
-        let pieces: Vec<Piece> = cells
-            .into_iter()
-            .filter(|c| c.piece().is_some())
-            .map(|c| c.piece().unwrap())
-            .collect();
+        let occupied = board.occupied_mask() & line;
+        let nb_pieces = occupied.count_ones() as usize;
 
         // No piece has been played -> score = 0
-        if pieces.is_empty() {
-            return Score::Point(0);
+        if nb_pieces == 0 {
+            return 0;
         }
 
-        let b_pieces = &pieces;
-
-        let points = vec![
-            //Color
-            b_pieces
-                .into_iter()
-                .filter(|f| f.color == Color::Dark)
-                .count(),
-            b_pieces
-                .into_iter()
-                .filter(|f| f.color == Color::White)
-                .count(),
-            //Height
-            b_pieces
-                .into_iter()
-                .filter(|f| f.height == Height::Tall)
-                .count(),
-            b_pieces
-                .into_iter()
-                .filter(|f| f.height == Height::Small)
-                .count(),
-            //Hole
-            b_pieces
-                .into_iter()
-                .filter(|f| f.hole == Hole::Full)
-                .count(),
-            b_pieces
-                .into_iter()
-                .filter(|f| f.hole == Hole::Empty)
-                .count(),
-            //Shape
-            b_pieces
-                .into_iter()
-                .filter(|f| f.shape == Shape::Circle)
-                .count(),
-            b_pieces
-                .into_iter()
-                .filter(|f| f.shape == Shape::Square)
-                .count(),
-        ];
+        // For each attribute, the number of pieces with the second value and with the first one
+        (0..A)
+            .flat_map(|attribute| {
+                let nb_set = (board.attribute_mask(attribute) & occupied).count_ones() as usize;
+                [nb_set, nb_pieces - nb_set]
+            })
+            .map(Score::calc_point)
+            .sum()
+    }
+
+    /// With the misère rules, completing a line loses, so we don't count alignements:
+    /// the score is the number of (available piece, empty cell) placements which don't complete any line.
+    /// The more safe placements are left, the less a player will be forced to complete a line
+    fn calc_misere_score<const W: usize, const H: usize, const A: usize>(board: &GenericBoard<W, H, A>) -> Score {
+        let empty_cells = board.empty_mask();
+        let available = board.available_mask();
+
+        let unsafe_placements: u32 = (0..u64::BITS as usize)
+            .filter(|cell| empty_cells & (1 << cell) != 0)
+            .map(|cell| {
+                // The pieces which would complete a line with the pieces already on it
+                let dangerous_pieces = board
+                    .lines()
+                    .iter()
+                    .filter(|line| *line & (1 << cell) != 0)
+                    .filter(|line| (board.occupied_mask() & *line).count_ones() == line.count_ones() - 1)
+                    .fold(0, |pieces, line| pieces | Score::completing_pieces(board, *line));
+
+                (dangerous_pieces & available).count_ones()
+            })
+            .sum();
 
-        Score::calc_point(points)
+        Score::Point((empty_cells.count_ones() * available.count_ones() - unsafe_placements) as i32)
     }
 
-    /// Calc the score for the current pieces alignement
-    fn calc_point(points: Vec<usize>) -> Score {
-        points
-            .into_iter()
-            .map(|p| {
-                /*
-                 * 0 or 1 piece = 0 point
-                 * 2 pieces = 1 points
-                 * 3 pieces = 2 points
-                 * 4 pieces = Win
-                 */
-                match p {
-                    2 => Score::Point(1),
-                    3 => Score::Point(2),
-                    4 => Score::Win,
-                    _ => Score::Point(0),
+    /// The numbers of the pieces which share an attribute value with all the pieces already on the line.
+    /// When only one cell of the line is empty, these are the pieces which would complete it
+    pub(crate) fn completing_pieces<const W: usize, const H: usize, const A: usize>(board: &GenericBoard<W, H, A>, line: u64) -> u64 {
+        let occupied = board.occupied_mask() & line;
+        (0..A)
+            .map(|attribute| {
+                let pieces_with_attribute = GenericPiece::<A>::numbers_with_attribute(attribute);
+                match (board.attribute_mask(attribute) & occupied).count_ones() {
+                    0 => !pieces_with_attribute,
+                    nb_set if nb_set == occupied.count_ones() => pieces_with_attribute,
+                    _ => 0,
                 }
             })
-            .sum::<Score>()
+            .fold(0, |pieces, attribute_pieces| pieces | attribute_pieces)
     }
 
-
-    fn has_win(score: &Vec<Score>) -> bool {
-        if score.last().unwrap() == &Score::Win {
-            return true;
-        }
-        false
+    /// The points of the pieces sharing an attribute value on a line (the line is not complete)
+    fn calc_point(nb_pieces: usize) -> i32 {
+        /*
+         * 0 or 1 piece = 0 point
+         * 2 pieces = 1 points
+         * 3 pieces = 2 points (on a line of 4 cells)
+         */
+        nb_pieces.saturating_sub(1) as i32
     }
 }
 
@@ -152,71 +177,45 @@ impl Default for Score {
 
 impl Display for Score {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let s_val = match self {
-            Self::Point(val) => {
-                format!("{} points", val)
-            },
-            Self::Win => {
-                "Winning board !".to_string()
-            },
-        };
-        write!(f, "{}", s_val)
+        match self {
+            Self::Point(val) => write!(f, "{} points", val),
+            Self::Win(plies) => write!(f, "Win in {}", plies),
+            Self::Loss(plies) => write!(f, "Loss in {}", plies),
+            Self::Draw => write!(f, "Draw"),
+        }
     }
 }
 
-impl PartialOrd for Score {
-
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-     }
-}
+/// The same score for the opponent, the distances don't change
+impl Neg for Score {
+    type Output = Score;
 
-// Implement comparison trait to allow score compare
-impl Ord for Score {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    fn neg(self) -> Score {
         match self {
-            Score::Point(self_val) => {
-                match other {
-                    Score::Point(other_val) => {
-                        return self_val.cmp(other_val);
-                    },
-                    Score::Win => {
-                        return Ordering::Less;
-                    }
-                }
-            },
-            Score::Win => {
-                match other {
-                    Score::Point(_) => {
-                        return Ordering::Greater;
-                    },
-                    Score::Win => {
-                        return Ordering::Equal;
-                    }
-                }
-            }
+            Score::Win(plies) => Score::Loss(plies),
+            Score::Loss(plies) => Score::Win(plies),
+            Score::Draw => Score::Draw,
+            Score::Point(point) => Score::Point(-point),
         }
     }
 }
 
-impl Sum for Score {
-    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
-        iter.fold(Self::Point(0), |x, y| {
-            if x == Score::Win || y == Score::Win {
-                return Score::Win;
-            }
-            if let (Score::Point(x_val), Score::Point(y_val)) = (x, y) {
-                return Score::Point(x_val + y_val);
-            }
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
-            Score::Point(0)
-        })
+/// Compare the values, a draw is just below no point
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.to_value(0).cmp(&other.to_value(0))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{board::{Board, Cell}, piece::Piece};
+    use crate::{board::{Board, Cell}, piece::Piece, rules::Rules};
 
     use super::Score;
 
@@ -267,12 +266,10 @@ mod tests {
             (Piece::from("WEXS"), Score::Point(8), Board::coordinate_to_index(0, 3).unwrap()),
         ];
 
-        for scenario in vec![
-            pieces_horizontal_second_line,
+        for scenario in [pieces_horizontal_second_line,
             pieces_vertical_third_line,
             pieces_diagonal_top_left_to_bottom_right,
-            pieces_diagonal_top_right_to_bottom_left,
-        ] {
+            pieces_diagonal_top_right_to_bottom_left] {
             test_scenario(scenario);
         }
     }
@@ -331,19 +328,105 @@ mod tests {
             (Piece::from("WEXS"), Score::Point(4), Board::coordinate_to_index(1, 2).unwrap()),
             (Piece::from("WFTS"), Score::Point(9), Board::coordinate_to_index(2, 1).unwrap()),
             (Piece::from("DEXS"), Score::Point(12), Board::coordinate_to_index(1, 3).unwrap()),
-            (Piece::from("DFTS"), Score::Win, Board::coordinate_to_index(1, 0).unwrap()),
+            // The line is complete, the player to move has lost
+            (Piece::from("DFTS"), Score::Loss(0), Board::coordinate_to_index(1, 0).unwrap()),
         ]);
     }
 
+    #[test]
+    pub fn test_calc_square_score_with_advanced_rules() {
+        let mut classic_board = Board::create();
+        let mut advanced_board = Board::create_with_rules(Rules::advanced());
+
+        for board in [&mut classic_board, &mut advanced_board] {
+            board.play(Piece::from("DFTC"), Cell::from_index(board, 0).unwrap()).unwrap();
+            board.remove(Piece::from("DFTC")).unwrap();
+            board.play(Piece::from("DFTS"), Cell::from_index(board, 1).unwrap()).unwrap();
+            board.remove(Piece::from("DFTS")).unwrap();
+        }
+
+        // The 2 pieces share 3 attributes on the first row, and also on the first square
+        assert_eq!(Score::calc_score(&classic_board), Score::Point(3));
+        assert_eq!(Score::calc_score(&advanced_board), Score::Point(6));
+    }
+
+    #[test]
+    pub fn test_calc_broken_diagonal_score_with_toroidal_rules() {
+        let mut classic_board = Board::create();
+        let mut toroidal_board = Board::create_with_rules(Rules::classic().with_toroidal(true));
+
+        for board in [&mut classic_board, &mut toroidal_board] {
+            // Cells 1 and 6 only share a broken diagonal
+            board.play(Piece::from("DFTC"), Cell::from_index(board, 1).unwrap()).unwrap();
+            board.remove(Piece::from("DFTC")).unwrap();
+            board.play(Piece::from("DFTS"), Cell::from_index(board, 6).unwrap()).unwrap();
+            board.remove(Piece::from("DFTS")).unwrap();
+        }
+
+        assert_eq!(Score::calc_score(&classic_board), Score::Point(0));
+        assert_eq!(Score::calc_score(&toroidal_board), Score::Point(3));
+    }
+
+    #[test]
+    pub fn test_calc_misere_score() {
+        let mut board = Board::create_with_rules(Rules::classic().with_misere(true));
+        assert_eq!(Score::calc_score(&board), Score::Point(16 * 16));
+
+        for (piece, cell) in [("DEXC", 0), ("DFXC", 1), ("DETC", 2)] {
+            board.play(Piece::from(piece), Cell::from_index(&board, cell).unwrap()).unwrap();
+            board.remove(Piece::from(piece)).unwrap();
+        }
+        // On the cell 3, the 5 dark pieces and the 5 circle pieces left (DFTC is both) would complete the line
+        assert_eq!(Score::calc_score(&board), Score::Point(13 * 13 - 9));
+
+        // The player who completed the line has lost, so the player to move has won
+        board.play(Piece::from("DFTC"), Cell::from_index(&board, 3).unwrap()).unwrap();
+        assert_eq!(Score::calc_score(&board), Score::Win(0));
+    }
+
     #[test]
     fn test_score_compare() {
         assert!(Score::Point(10) < Score::Point(20));
         assert!(Score::Point(1) > Score::Point(0));
+        assert!(Score::Point(-1) < Score::Point(0));
         assert!(Score::Point(0) >= Score::Point(0));
+        assert!(Score::Loss(1) < Score::Point(-50));
+        assert!(Score::Loss(1) < Score::Win(1));
         assert!(Score::Point(50) == Score::Point(50));
+        assert!(Score::Point(50) < Score::Win(10));
+        assert!(Score::Draw < Score::Point(0) && Score::Draw > Score::Point(-1));
+
+        // A quick win is better, a slow loss is better
+        assert!(Score::Win(1) > Score::Win(3));
+        assert!(Score::Loss(1) < Score::Loss(3));
+    }
+
+    #[test]
+    fn test_score_for_previous_player() {
+        assert_eq!(Score::Loss(0).for_previous_player(), Score::Win(1));
+        assert_eq!(Score::Win(1).for_previous_player(), Score::Loss(2));
+        assert_eq!(Score::Point(7).for_previous_player(), Score::Point(-7));
+        assert_eq!(Score::Draw.for_previous_player(), Score::Draw);
+        assert_eq!(-Score::Win(3), Score::Loss(3));
+    }
+
+    #[test]
+    fn test_score_values() {
+        for score in [Score::Win(1), Score::Loss(4), Score::Draw, Score::Point(0), Score::Point(-12), Score::Point(30)] {
+            for ply in [0, 3] {
+                assert_eq!(Score::from_value(score.to_value(ply), ply), score);
+            }
+        }
+        assert_eq!(Score::Win(1).to_value(2), Score::Win(3).to_value(0));
+        assert!(Score::Point(-1).to_value(0) < Score::Draw.to_value(0));
+        assert!(Score::Draw.to_value(0) < Score::Point(0).to_value(0));
+    }
 
-        assert!(Score::Point(50) < Score::Win);
-        assert!(Score::Win == Score::Win);
+    #[test]
+    fn test_score_value_of_another_ply() {
+        // The loss is one placement before the ply 2: it's read as a loss now
+        assert_eq!(Score::from_value(Score::Loss(1).to_value(0), 2), Score::Loss(0));
+        assert_eq!(Score::from_value(Score::Win(0).to_value(0), 3), Score::Win(0));
     }
 
     #[test]
@@ -366,4 +449,14 @@ mod tests {
 
         info!("Score: {} nanosec", now.elapsed().as_nanos());
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_score() {
+        for score in [Score::Loss(2), Score::Point(-12), Score::Draw, Score::Win(1)] {
+            let json = serde_json::to_string(&score).unwrap();
+            assert_eq!(serde_json::from_str::<Score>(&json).unwrap(), score);
+        }
+        assert_eq!(serde_json::to_string(&Score::Point(3)).unwrap(), r#"{"Point":3}"#);
+    }
 }
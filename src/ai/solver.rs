@@ -0,0 +1,406 @@
+//! # Endgame solver
+//! When only a few cells are empty, the whole game can be searched: the solver proves if a placement
+//! or a gift wins, loses or draws with perfect play, and in how many placements.
+//! It doesn't use `Score` at all.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use crate::ai::get_moves;
+use crate::ai::transposition::Bound;
+use crate::ai::Board;
+use crate::ai::ErrorGame;
+use crate::ai::Piece;
+use crate::ai::Strategy;
+use crate::r#move::Move;
+use crate::rules::Rules;
+
+/// The default threshold: the game is solved when less than 9 cells are empty
+pub const DEFAULT_SOLVER_THRESHOLD: usize = 9;
+
+/// The value of a win in the search, minus the number of placements to reach it.
+/// A loss is the opposite, a draw is 0
+const WIN: i32 = 1000;
+const INFINITY: i32 = WIN + 1;
+
+/// The result of the game with perfect play, for the player who does the action,
+/// with the number of placements until the end (the placement of the action included)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Win(usize),
+    Draw,
+    Loss(usize),
+}
+
+impl Outcome {
+    /// The value of the search, with the distances counted from the node where the value has been found
+    fn from_value(value: i32) -> Outcome {
+        match value.cmp(&0) {
+            Ordering::Greater => Outcome::Win((WIN - value) as usize),
+            Ordering::Equal => Outcome::Draw,
+            Ordering::Less => Outcome::Loss((WIN + value) as usize),
+        }
+    }
+
+    fn value(&self) -> i32 {
+        match self {
+            Outcome::Win(distance) => WIN - *distance as i32,
+            Outcome::Draw => 0,
+            Outcome::Loss(distance) => -(WIN - *distance as i32),
+        }
+    }
+}
+
+/// A quick win is better than a slow one, a slow loss is better than a quick one
+impl Ord for Outcome {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.value().cmp(&other.value())
+    }
+}
+
+impl PartialOrd for Outcome {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Outcome::Win(distance) => write!(f, "Win in {}", distance),
+            Outcome::Draw => write!(f, "Draw"),
+            Outcome::Loss(distance) => write!(f, "Loss in {}", distance),
+        }
+    }
+}
+
+/// Exact search of the end of the game (negamax with alpha-beta pruning and a transposition table).
+/// A turn is the placement of the piece received then the gift of a piece to the opponent
+#[derive(Debug, Clone, Default)]
+pub struct Solver {
+    /// The game is solved when the number of empty cells is lower
+    threshold: usize,
+    /// The values of the turns already searched: (hash of the board, piece to place, rules) -> (value, bound).
+    /// The distances of the values are counted from the turn
+    table: HashMap<(u64, u8, Rules), (i32, Bound)>,
+    /// The number of turns searched since the creation
+    nodes: usize,
+}
+
+impl Solver {
+    pub fn name() -> String {
+        String::from("Solver")
+    }
+
+    pub fn new() -> Solver {
+        Solver::with_threshold(DEFAULT_SOLVER_THRESHOLD)
+    }
+
+    /// Solve the game when less than `threshold` cells are empty
+    pub fn with_threshold(threshold: usize) -> Solver {
+        Solver {
+            threshold,
+            ..Default::default()
+        }
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    /// Is the board near enough the end of the game to be solved ?
+    pub fn can_solve(&self, board: &Board) -> bool {
+        board.get_empty_cells().len() < self.threshold
+    }
+
+    /// The outcome of every placement of the piece (of every piece when none is given) for the player who places it
+    pub fn placement_outcomes(&mut self, board: &Board, piece: Option<Piece>) -> Vec<(Move, Outcome)> {
+        let mut board = board.clone();
+        get_moves(&board, piece)
+            .into_iter()
+            .map(|m| {
+                board.play_and_remove_piece(&m).unwrap();
+                let value = self.after_placement(&mut board, &m, 0, -INFINITY, INFINITY);
+                board.unplay(&m).unwrap();
+                (m, Outcome::from_value(value))
+            })
+            .collect()
+    }
+
+    /// The outcome of every piece which can be given, for the player who gives it
+    pub fn gift_outcomes(&mut self, board: &Board) -> Vec<(Piece, Outcome)> {
+        let mut board = board.clone();
+        board
+            .get_available_pieces()
+            .into_values()
+            .map(|piece| (piece, Outcome::from_value(-self.turn(&mut board, piece, 0, -INFINITY, INFINITY))))
+            .collect()
+    }
+
+    /// The value of the turn for the player who places the piece, `ply` is the number of placements since the root.
+    /// The board is restored before returning
+    fn turn(&mut self, board: &mut Board, piece: Piece, ply: i32, mut alpha: i32, mut beta: i32) -> i32 {
+        self.nodes += 1;
+        let misere = board.rules().misere();
+        let moves = board.get_available_moves_from_piece(piece);
+
+        // A Quarto now is the best result, no need to search further
+        if !misere {
+            for m in &moves {
+                board.play_and_remove_piece(m).unwrap();
+                let completes_line = !board.completed_lines(m.cell().to_index()).is_empty();
+                board.unplay(m).unwrap();
+                if completes_line {
+                    return WIN - (ply + 1);
+                }
+            }
+        }
+
+        let key = (board.zobrist(), piece.number(), board.rules());
+        let (original_alpha, original_beta) = (alpha, beta);
+        if let Some((value, bound)) = self.table.get(&key) {
+            let value = Solver::from_table(*value, ply);
+            match bound {
+                Bound::Exact => return value,
+                Bound::Lower => alpha = alpha.max(value),
+                Bound::Upper => beta = beta.min(value),
+            }
+            if alpha >= beta {
+                return value;
+            }
+        }
+
+        let mut best = -INFINITY;
+        for m in &moves {
+            board.play_and_remove_piece(m).unwrap();
+            let value = self.after_placement(board, m, ply, alpha, beta);
+            board.unplay(m).unwrap();
+
+            best = best.max(value);
+            alpha = alpha.max(value);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best <= original_alpha {
+            Bound::Upper
+        } else if best >= original_beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.table.insert(key, (Solver::to_table(best, ply), bound));
+        best
+    }
+
+    /// The value for the player who has just played the move: the end of the game, or the best gift
+    fn after_placement(&mut self, board: &mut Board, m: &Move, ply: i32, mut alpha: i32, beta: i32) -> i32 {
+        if !board.completed_lines(m.cell().to_index()).is_empty() {
+            return if board.rules().misere() { -(WIN - (ply + 1)) } else { WIN - (ply + 1) };
+        }
+        if !board.can_play_another_turn() {
+            return 0;
+        }
+
+        // The pieces which let the opponent complete a line are given last
+        let winning_pieces = board.winning_pieces_mask();
+        let mut pieces: Vec<Piece> = board.get_available_pieces().into_values().collect();
+        pieces.sort_by_key(|piece| winning_pieces & (1 << piece.number()) != 0);
+
+        let mut best = -INFINITY;
+        for piece in pieces {
+            let value = -self.turn(board, piece, ply + 1, -beta, -alpha);
+            best = best.max(value);
+            alpha = alpha.max(value);
+            if alpha >= beta {
+                break;
+            }
+        }
+        best
+    }
+
+    /// The values are stored with the distances counted from the turn, so they can be reused at another ply
+    fn to_table(value: i32, ply: i32) -> i32 {
+        match value.cmp(&0) {
+            Ordering::Greater => value + ply,
+            Ordering::Equal => 0,
+            Ordering::Less => value - ply,
+        }
+    }
+
+    fn from_table(value: i32, ply: i32) -> i32 {
+        match value.cmp(&0) {
+            Ordering::Greater => value - ply,
+            Ordering::Equal => 0,
+            Ordering::Less => value + ply,
+        }
+    }
+}
+
+impl Strategy for Solver {
+    fn name(&self) -> String {
+        Solver::name()
+    }
+
+    /// The placement with the best outcome (the first one when several are as good)
+    fn calc_move(&mut self, board: &Board, piece: Option<Piece>) -> Result<Move, ErrorGame> {
+        let outcomes = self.placement_outcomes(board, piece);
+        let best = outcomes.iter().map(|(_, outcome)| *outcome).max();
+        info!("Solver outcome = {:?}", best);
+
+        outcomes
+            .into_iter()
+            .find(|(_, outcome)| Some(*outcome) == best)
+            .map(|(m, _)| m)
+            .ok_or_else(|| ErrorGame::NoBestMove {
+                depth: board.get_empty_cells().len(),
+                position: board.to_notation(),
+            })
+    }
+
+    /// The gift with the best outcome (the first one when several are as good)
+    fn choose_piece_for_opponent(&mut self, board: &Board) -> Piece {
+        let outcomes = self.gift_outcomes(board);
+        let best = outcomes.iter().map(|(_, outcome)| *outcome).max();
+        info!("Solver outcome = {:?}", best);
+
+        outcomes
+            .into_iter()
+            .find(|(_, outcome)| Some(*outcome) == best)
+            .map(|(piece, _)| piece)
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BoardState, Cell};
+
+    fn board_from(moves: &[(&str, usize)], rules: Rules) -> Board {
+        let mut board = Board::create_with_rules(rules);
+        for (piece, cell) in moves {
+            board
+                .play_and_remove_piece(&Move::new(Piece::from(*piece), Cell::new(*cell).unwrap()))
+                .unwrap();
+        }
+        board
+    }
+
+    /// The exact value of the turn, without pruning nor table
+    fn brute_force(board: &mut Board, piece: Piece) -> Outcome {
+        board
+            .get_available_moves_from_piece(piece)
+            .iter()
+            .map(|m| {
+                board.play_and_remove_piece(m).unwrap();
+                let outcome = if !board.completed_lines(m.cell().to_index()).is_empty() {
+                    if board.rules().misere() {
+                        Outcome::Loss(1)
+                    } else {
+                        Outcome::Win(1)
+                    }
+                } else if !board.can_play_another_turn() {
+                    Outcome::Draw
+                } else {
+                    let pieces: Vec<Piece> = board.get_available_pieces().into_values().collect();
+                    pieces
+                        .into_iter()
+                        .map(|piece| match brute_force(board, piece) {
+                            Outcome::Win(distance) => Outcome::Loss(distance + 1),
+                            Outcome::Draw => Outcome::Draw,
+                            Outcome::Loss(distance) => Outcome::Win(distance + 1),
+                        })
+                        .max()
+                        .unwrap()
+                };
+                board.unplay(m).unwrap();
+                outcome
+            })
+            .max()
+            .unwrap()
+    }
+
+    fn end_game(rules: Rules) -> Board {
+        board_from(
+            &[
+                ("DFXS", 1),
+                ("DFXC", 2),
+                ("DFTS", 4),
+                ("WEXC", 6),
+                ("WFXS", 7),
+                ("DFTC", 8),
+                ("DEXS", 9),
+                ("WEXS", 11),
+                ("WETC", 13),
+                ("WETS", 14),
+            ],
+            rules,
+        )
+    }
+
+    #[test]
+    fn test_outcome_order() {
+        assert!(Outcome::Win(1) > Outcome::Win(3));
+        assert!(Outcome::Win(7) > Outcome::Draw);
+        assert!(Outcome::Draw > Outcome::Loss(7));
+        assert!(Outcome::Loss(3) > Outcome::Loss(1));
+        assert_eq!(Outcome::Loss(2).to_string(), "Loss in 2");
+    }
+
+    #[test]
+    fn test_same_outcomes_as_brute_force() {
+        for rules in [Rules::classic(), Rules::classic().with_misere(true), Rules::advanced()] {
+            let board = end_game(rules);
+            assert_eq!(board.board_state(), BoardState::GameInProgress);
+            let mut solver = Solver::new();
+
+            for (piece, outcome) in solver.gift_outcomes(&board) {
+                let expected = match brute_force(&mut board.clone(), piece) {
+                    Outcome::Win(distance) => Outcome::Loss(distance),
+                    Outcome::Draw => Outcome::Draw,
+                    Outcome::Loss(distance) => Outcome::Win(distance),
+                };
+                assert_eq!(outcome, expected, "{} with {:?}", piece, rules);
+            }
+        }
+    }
+
+    #[test]
+    fn test_play_the_quickest_win() {
+        // With the advanced rules, every piece left completes a line or a square
+        let mut board = end_game(Rules::advanced());
+        let piece = *board.get_available_pieces().values().next().unwrap();
+        assert_ne!(board.winning_pieces_mask() & (1 << piece.number()), 0);
+
+        let mut solver = Solver::new();
+        let m = solver.calc_move(&board, Some(piece)).unwrap();
+        board.play_and_remove_piece(&m).unwrap();
+        assert!(!board.completed_lines(m.cell().to_index()).is_empty());
+    }
+
+    #[test]
+    fn test_give_the_piece_which_loses_later() {
+        let board = end_game(Rules::classic());
+        let mut solver = Solver::new();
+
+        let outcomes = solver.gift_outcomes(&board);
+        assert_eq!(outcomes[0], (Piece::from("WFXC"), Outcome::Loss(5)));
+        assert_eq!(outcomes[1], (Piece::from("WFTC"), Outcome::Loss(3)));
+        assert_eq!(solver.choose_piece_for_opponent(&board), Piece::from("WFXC"));
+    }
+
+    #[test]
+    fn test_can_solve() {
+        assert!(!Solver::new().can_solve(&Board::create()));
+        assert!(Solver::new().can_solve(&end_game(Rules::classic())));
+        assert!(!Solver::with_threshold(6).can_solve(&end_game(Rules::classic())));
+        assert!(Solver::with_threshold(7).can_solve(&end_game(Rules::classic())));
+    }
+}
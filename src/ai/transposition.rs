@@ -0,0 +1,304 @@
+//! # Transposition table
+//! The same position is reached by many move orders during a search.
+//! The table keeps the score of the positions already searched, keyed by the zobrist hash of the board
+//! and the piece to place, so a search can reuse it instead of searching the position again.
+//! The scores are the ones of the player to move, the distances of the wins and losses counted from the position.
+//! The table has a fixed number of slots: a new entry takes the slot of the old one.
+//! With the `parallel` feature, a `SharedTable` lets the threads searching the root moves of `MinMaxTree` use the same table.
+
+use crate::{ai::Score, board::Board, piece::Piece, rules::Rules, zobrist};
+
+/// The default number of slots of the table
+pub const DEFAULT_TABLE_CAPACITY: usize = 1 << 16;
+
+/// What the stored score says about the real score of the position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Bound {
+    /// The score is the real score
+    Exact,
+    /// The real score is at least the score (the search has been cut because the score was too good)
+    Lower,
+    /// The real score is at most the score (no move reached the expected score)
+    Upper,
+}
+
+/// A position already searched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    /// The zobrist hash of the board, with the piece to place
+    hash: u64,
+    /// The rules of the board, the same pieces don't have the same score with other rules
+    rules: Rules,
+    /// The depth of the search from this position
+    depth: usize,
+    score: Score,
+    bound: Bound,
+}
+
+impl Entry {
+    pub fn score(&self) -> Score {
+        self.score
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn bound(&self) -> Bound {
+        self.bound
+    }
+}
+
+/// How much the table has been useful
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TableStats {
+    /// The number of times a position has been looked for
+    pub probes: usize,
+    /// The number of times the position has been found
+    pub hits: usize,
+    /// The number of entries stored
+    pub stores: usize,
+    /// The number of entries which took the slot of another position
+    pub replacements: usize,
+}
+
+impl TableStats {
+    /// The part of the probes which found the position, between 0 and 1
+    pub fn hit_rate(&self) -> f64 {
+        if self.probes == 0 {
+            0.0
+        } else {
+            self.hits as f64 / self.probes as f64
+        }
+    }
+}
+
+/// The transposition table, bounded by its number of slots.
+/// The slots are only allocated on the first store, so an unused table costs nothing
+#[derive(Clone, PartialEq, Eq)]
+pub struct TranspositionTable {
+    capacity: usize,
+    slots: Vec<Option<Entry>>,
+    stats: TableStats,
+}
+
+/// The entries are not displayed, there are too many of them
+impl std::fmt::Debug for TranspositionTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TranspositionTable")
+            .field("capacity", &self.capacity)
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::new(DEFAULT_TABLE_CAPACITY)
+    }
+}
+
+impl TranspositionTable {
+    /// Create a table with this number of slots. A table without slot never stores anything
+    pub fn new(capacity: usize) -> TranspositionTable {
+        TranspositionTable {
+            capacity,
+            slots: vec![],
+            stats: TableStats::default(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The number of positions currently stored
+    pub fn len(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn stats(&self) -> TableStats {
+        self.stats
+    }
+
+    /// Remove every entry, the statistics are kept
+    pub fn clear(&mut self) {
+        self.slots.clear();
+    }
+
+    /// The hash of the board with the piece to place (the piece in hand of the board is replaced)
+    fn hash(board: &Board, piece: Option<Piece>) -> u64 {
+        [board.hand(), piece]
+            .into_iter()
+            .flatten()
+            .fold(board.zobrist(), |hash, piece| hash ^ zobrist::piece_in_hand(piece.number()))
+    }
+
+    /// The slot of a position. The depth is mixed in, so the searches of the same board
+    /// at different depths don't always fight for the same slot
+    fn slot(&self, hash: u64, depth: usize) -> usize {
+        let key = hash ^ (depth as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        (key % self.capacity as u64) as usize
+    }
+
+    /// Look for the score of the board searched at this depth, with this piece to place
+    pub fn probe(&mut self, board: &Board, piece: Option<Piece>, depth: usize) -> Option<Entry> {
+        self.stats.probes += 1;
+        if self.slots.is_empty() {
+            return None;
+        }
+
+        let hash = TranspositionTable::hash(board, piece);
+        let entry = self.slots[self.slot(hash, depth)].filter(|entry| {
+            entry.hash == hash && entry.rules == board.rules() && entry.depth == depth
+        });
+        if entry.is_some() {
+            self.stats.hits += 1;
+        }
+        entry
+    }
+
+    /// Store the score of the board searched at this depth with this piece to place, it replaces what was in the slot
+    pub fn store(&mut self, board: &Board, piece: Option<Piece>, depth: usize, score: Score, bound: Bound) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.slots.is_empty() {
+            self.slots = vec![None; self.capacity];
+        }
+
+        let hash = TranspositionTable::hash(board, piece);
+        let slot = self.slot(hash, depth);
+        if self.slots[slot].is_some_and(|entry| entry.hash != hash) {
+            self.stats.replacements += 1;
+        }
+        self.slots[slot] = Some(Entry {
+            hash,
+            rules: board.rules(),
+            depth,
+            score,
+            bound,
+        });
+        self.stats.stores += 1;
+    }
+}
+
+/// What a search needs from a table, so it can search with a table of its own or a shared one
+pub trait Table {
+    fn probe(&mut self, board: &Board, piece: Option<Piece>, depth: usize) -> Option<Entry>;
+    fn store(&mut self, board: &Board, piece: Option<Piece>, depth: usize, score: Score, bound: Bound);
+}
+
+impl Table for TranspositionTable {
+    fn probe(&mut self, board: &Board, piece: Option<Piece>, depth: usize) -> Option<Entry> {
+        TranspositionTable::probe(self, board, piece, depth)
+    }
+
+    fn store(&mut self, board: &Board, piece: Option<Piece>, depth: usize, score: Score, bound: Bound) {
+        TranspositionTable::store(self, board, piece, depth, score, bound)
+    }
+}
+
+/// A table used by several threads at the same time, each access locks it
+#[cfg(feature = "parallel")]
+#[derive(Debug)]
+pub struct SharedTable {
+    table: std::sync::Mutex<TranspositionTable>,
+}
+
+#[cfg(feature = "parallel")]
+impl SharedTable {
+    pub fn new(table: TranspositionTable) -> SharedTable {
+        SharedTable {
+            table: std::sync::Mutex::new(table),
+        }
+    }
+
+    /// Take back the table when the threads are done
+    pub fn into_inner(self) -> TranspositionTable {
+        self.table.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, TranspositionTable> {
+        self.table.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl Table for &SharedTable {
+    fn probe(&mut self, board: &Board, piece: Option<Piece>, depth: usize) -> Option<Entry> {
+        self.lock().probe(board, piece, depth)
+    }
+
+    fn store(&mut self, board: &Board, piece: Option<Piece>, depth: usize, score: Score, bound: Bound) {
+        self.lock().store(board, piece, depth, score, bound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        board::Cell,
+        piece::Piece,
+        r#move::Move,
+    };
+
+    #[test]
+    fn test_probe_and_store() {
+        let mut board = Board::create();
+        let mut table = TranspositionTable::new(64);
+        let piece = Some(Piece::from("WFTS"));
+        assert_eq!(table.probe(&board, piece, 2), None);
+
+        table.store(&board, piece, 2, Score::Point(1), Bound::Exact);
+        assert_eq!(table.probe(&board, piece, 2).map(|entry| entry.score()), Some(Score::Point(1)));
+        assert_eq!(table.probe(&board, piece, 1), None);
+        assert_eq!(table.probe(&board, Some(Piece::from("DFTS")), 2), None);
+
+        board
+            .play_and_remove_piece(&Move::new(Piece::from("DEXC"), Cell::new(0).unwrap()))
+            .unwrap();
+        assert_eq!(table.probe(&board, piece, 2), None);
+
+        let stats = table.stats();
+        assert_eq!((stats.probes, stats.hits, stats.stores), (5, 1, 1));
+        assert_eq!(stats.hit_rate(), 0.2);
+    }
+
+    #[test]
+    fn test_piece_in_hand_of_the_board() {
+        let mut board = Board::create();
+        let mut table = TranspositionTable::new(64);
+        table.store(&board, Some(Piece::from("WFTS")), 2, Score::Point(1), Bound::Exact);
+
+        // The piece given to the table is the one to place, not the piece in hand of the board
+        board.set_hand(Some(Piece::from("DFTS")));
+        assert!(table.probe(&board, Some(Piece::from("WFTS")), 2).is_some());
+        assert!(table.probe(&board, Some(Piece::from("DFTS")), 2).is_none());
+    }
+
+    #[test]
+    fn test_table_is_bounded() {
+        let mut table = TranspositionTable::new(8);
+        let mut board = Board::create();
+        for i in 0..16 {
+            let piece = *board.get_available_pieces().values().next().unwrap();
+            board.play_and_remove_piece(&Move::new(piece, Cell::new(i).unwrap())).unwrap();
+            table.store(&board, None, 1, Score::Point(i as i32), Bound::Exact);
+        }
+
+        assert!(table.len() <= 8);
+        assert_eq!(table.stats().stores, 16);
+        assert!(table.stats().replacements >= 8);
+
+        let mut disabled = TranspositionTable::new(0);
+        disabled.store(&board, None, 1, Score::Win(1), Bound::Exact);
+        assert_eq!(disabled.probe(&board, None, 1), None);
+        assert!(disabled.is_empty());
+    }
+}
@@ -1,10 +1,5 @@
-use crate::{error::ErrorGame};
-use enum_iterator::IntoEnumIterator;
-use std::{
-    collections::BTreeMap,
-    fmt::Display,
-    ops::{Index, IndexMut},
-};
+use crate::error::ErrorGame;
+use std::{collections::BTreeMap, fmt::Display, hash::Hash, ops::Index, sync::Arc};
 
 //https://doc.rust-lang.org/reference/conditional-compilation.html
 // let my_directory = if cfg!(windows) {
@@ -15,104 +10,144 @@ use std::{
 // #[cfg(target_arch = "wasm32")]
 // use prettytable::{Cell as pCell, Row as pRow, Table as pTable};
 
-use crate::piece::{Color, Height, Hole, Piece, PieceFeature, Shape};
-use crate::r#move::Move;
+use crate::piece::{GenericPiece, Piece, MAX_ATTRIBUTES};
+use crate::r#move::GenericMove;
+use crate::rules::Rules;
+use crate::zobrist;
 
 pub const WIDTH_BOARD: usize = 4;
 pub const HEIGHT_BOARD: usize = 4;
 
-pub trait BoardIndex {
+/// The number of attributes which caracterise a piece (color, hole, height and shape)
+pub const NB_ATTRIBUTES: usize = 4;
+
+/// The classic Quarto board: 4x4 cells and 16 pieces with 4 attributes
+pub type Board = GenericBoard<WIDTH_BOARD, HEIGHT_BOARD, NB_ATTRIBUTES>;
+
+/// A cell of the classic Quarto board
+pub type Cell = GenericCell<WIDTH_BOARD, HEIGHT_BOARD, NB_ATTRIBUTES>;
+
+/// The largest number of cells of a board, one bit per cell in the masks
+const MAX_CELLS: usize = u64::BITS as usize;
+
+/// Return the mask with the `nb` first bits set
+pub(crate) fn full_mask(nb: usize) -> u64 {
+    if nb >= u64::BITS as usize {
+        u64::MAX
+    } else {
+        (1 << nb) - 1
+    }
+}
+
+pub trait BoardIndex<const W: usize, const H: usize, const A: usize> {
     /// Create the trait from specified index
-    fn from_index(board: &Board, index: usize) -> Result<Self, ErrorGame>
+    fn from_index(board: &GenericBoard<W, H, A>, index: usize) -> Result<Self, ErrorGame>
     where
         Self: Sized;
 
     /// Return the index from the struct
-    fn to_index(&self, board: &Board) -> Result<usize, ErrorGame>;
+    fn to_index(&self, board: &GenericBoard<W, H, A>) -> Result<usize, ErrorGame>;
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Represent the state of the game
-pub enum BoardState {
+pub enum BoardState<C = Cell> {
     /// No current winner and game currently in progress
     GameInProgress,
 
     /// We found a win combinaison
-    Win(BTreeMap<usize, Cell>),
+    Win(BTreeMap<usize, C>),
+
+    /// A line has been completed with the misère rules: the player who completed it loses
+    Loss(BTreeMap<usize, C>),
 
     /// No piece left, it's a draw
     Draw,
 }
 
+/// The board is stored as bitboards: each mask has one bit per cell (or per piece number).
+/// `W` x `H` is the size of the board and `A` the number of attributes of the pieces (so there is 2^A pieces)
 #[derive(Clone, Eq, PartialEq)]
-pub struct Board {
-    /// The x16 cells of the board
-    cells: BTreeMap<usize, Cell>,
+pub struct GenericBoard<const W: usize, const H: usize, const A: usize> {
+    /// The W x H cells of the board, kept in sync with the masks to give access by reference.
+    /// The array has room for the largest board, so cloning the board doesn't allocate: only the first W x H cells are used
+    cells: [GenericCell<W, H, A>; MAX_CELLS],
+
+    /// Bit `i` is set when a piece has been played on the cell `i`
+    occupied: u64,
+
+    /// One mask per attribute, in the piece number order (color, hole, height, shape for the classic pieces).
+    /// Bit `i` is set when the piece on the cell `i` has the second value of the attribute (dark, full, tall, square)
+    attributes: [u64; A],
 
-    /// Pieces which has not been played yet
-    available_pieces: BTreeMap<usize, Piece>,
+    /// Bit `n` is set while the piece number `n` has not been played yet
+    available: u64,
+
+    /// The rules of the game
+    rules: Rules,
+
+    /// The lines which win with these rules, shared by every board with the same rules and size
+    lines: Arc<[u64]>,
+
+    /// The piece handed over to the player who has to place it, if any
+    hand: Option<GenericPiece<A>>,
+
+    /// The Zobrist hash of the position, updated on each change
+    zobrist: u64,
 }
 
-impl Board {
-    /// Generate all pieces of the game
-    fn generate_all_pieces() -> BTreeMap<usize, Piece> {
-        let mut pieces = BTreeMap::new();
-        let mut key = 0;
-
-        //Loop over the enums to get all the possibilities
-        for color in Color::into_enum_iter() {
-            for hole in Hole::into_enum_iter() {
-                for height in Height::into_enum_iter() {
-                    for shape in Shape::into_enum_iter() {
-                        pieces.insert(key, Piece::new(color, hole, height, shape));
-                        key += 1;
-                    }
-                }
-            }
-        }
+/// Iterate over the index of each bit set in the mask
+fn bits(mask: u64) -> impl Iterator<Item = usize> {
+    (0..u64::BITS as usize).filter(move |i| mask & (1 << i) != 0)
+}
 
-        assert_eq!(pieces.len(), WIDTH_BOARD * HEIGHT_BOARD);
-        trace!("All pieces have been generated");
-        pieces
-    }
+impl<const W: usize, const H: usize, const A: usize> GenericBoard<W, H, A> {
+    /// The number of cells of the board
+    pub const NB_CELLS: usize = W * H;
 
-    /// Create the board with (WIDTH_BOARD * HEIGHT_BOARD) empty cells
-    fn generate_all_cells() -> BTreeMap<usize, Cell> {
-        let mut cells = BTreeMap::new();
+    /// The number of pieces (each attribute has two values)
+    pub const NB_PIECES: usize = 1 << A;
 
-        for i in 0..WIDTH_BOARD * HEIGHT_BOARD {
-            cells.insert(i, Cell::new(i).unwrap());
-        }
+    /// The cells and the pieces have to fit in the 64 bits masks
+    const SIZE_IS_VALID: () = assert!(
+        W > 0 && H > 0 && W * H <= 64 && A > 0 && A <= MAX_ATTRIBUTES,
+        "The board is limited to 64 cells and 6 attributes"
+    );
+
+    /// Create the board with (W * H) empty cells, the unused cells of the array stay on the first one
+    fn generate_all_cells() -> [GenericCell<W, H, A>; MAX_CELLS] {
+        let cells = std::array::from_fn(|i| GenericCell::new(i).unwrap_or(GenericCell { piece: None, x: 0, y: 0 }));
 
-        assert_eq!(cells.len(), WIDTH_BOARD * HEIGHT_BOARD);
         trace!("All cells have been generated");
         cells
     }
 
     /// Return index from (x; y) coordinate
     pub fn coordinate_to_index(x: usize, y: usize) -> Result<usize, ErrorGame> {
-        if x >= WIDTH_BOARD || y >= HEIGHT_BOARD {
+        if x >= W || y >= H {
             return Err(ErrorGame::IndexOutOfBound);
         }
-        Ok(y * HEIGHT_BOARD + x)
+        Ok(y * W + x)
     }
 
     /// Return (x; y) coordinate from index
     pub fn index_to_coordinate(index: usize) -> Result<(usize, usize), ErrorGame> {
-        if index >= WIDTH_BOARD * HEIGHT_BOARD {
+        if index >= Self::NB_CELLS {
             return Err(ErrorGame::IndexOutOfBound);
         }
-        Ok((index % WIDTH_BOARD, index / HEIGHT_BOARD))
+        Ok((index % W, index / W))
     }
 
     /// Return the (top left to bottom right) (top right to bottom left) list of cells
-    pub fn get_diagonal_cells(board: &Board) -> (Vec<Cell>, Vec<Cell>) {
-        let mut diagonal_cells_top_left_bottom_right: Vec<Cell> = vec![];
-        let mut diagonal_cells_top_right_bottom_left: Vec<Cell> = vec![];
-        for i in 0..WIDTH_BOARD {
-            diagonal_cells_top_left_bottom_right.push(Cell::from_coordinate(board, i, i).unwrap());
+    pub fn get_diagonal_cells(board: &Self) -> (Vec<GenericCell<W, H, A>>, Vec<GenericCell<W, H, A>>) {
+        let mut diagonal_cells_top_left_bottom_right = vec![];
+        let mut diagonal_cells_top_right_bottom_left = vec![];
+        let size = W.min(H);
+        for i in 0..size {
+            diagonal_cells_top_left_bottom_right.push(GenericCell::from_coordinate(board, i, i).unwrap());
             diagonal_cells_top_right_bottom_left
-                .push(Cell::from_coordinate(board, WIDTH_BOARD - i - 1, i).unwrap());
+                .push(GenericCell::from_coordinate(board, size - i - 1, i).unwrap());
         }
 
         (
@@ -122,17 +157,79 @@ impl Board {
     }
 }
 
-impl Board {
+impl<const W: usize, const H: usize, const A: usize> GenericBoard<W, H, A> {
     ///Create a new board to start a game
-    pub fn create() -> Board {
-        Board {
-            cells: Board::generate_all_cells(),
-            available_pieces: Board::generate_all_pieces(),
+    pub fn create() -> Self {
+        Self::create_with_rules(Rules::default())
+    }
+
+    ///Create a new board to start a game with specific rules
+    pub fn create_with_rules(rules: Rules) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let _ = Self::SIZE_IS_VALID;
+
+        GenericBoard {
+            cells: Self::generate_all_cells(),
+            occupied: 0,
+            attributes: [0; A],
+            available: full_mask(Self::NB_PIECES),
+            rules,
+            lines: rules.shared_lines(W, H),
+            hand: None,
+            zobrist: 0,
         }
     }
 
+    /// The W x H cells of the board, without the unused part of the array
+    fn used_cells(&self) -> &[GenericCell<W, H, A>] {
+        &self.cells[..Self::NB_CELLS]
+    }
+
+    /// The rules of the game
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
+    /// The lines to complete to win the game, as cell masks
+    pub fn lines(&self) -> &[u64] {
+        &self.lines
+    }
+
+    /// The piece handed over to the player who has to place it, if any
+    pub fn hand(&self) -> Option<GenericPiece<A>> {
+        self.hand
+    }
+
+    /// Hand over the piece the next player has to place (the piece is not checked, it's up to the game)
+    pub(crate) fn set_hand(&mut self, hand: Option<GenericPiece<A>>) {
+        for piece in [self.hand, hand].into_iter().flatten() {
+            self.zobrist ^= zobrist::piece_in_hand(piece.number());
+        }
+        self.hand = hand;
+    }
+
+    /// The Zobrist hash of the position: the pieces on the cells, the pieces played and the piece in hand.
+    /// It is updated on each move, so it's a cheap key for the positions
+    pub fn zobrist(&self) -> u64 {
+        self.zobrist
+    }
+
+    /// Compute the Zobrist hash from scratch
+    #[cfg(any(test, feature = "serde"))]
+    fn compute_zobrist(&self) -> u64 {
+        let cells = self
+            .used_cells()
+            .iter()
+            .enumerate()
+            .filter_map(|(i, cell)| cell.piece.map(|piece| zobrist::piece_on_cell(i, piece.number())));
+        let played = bits(!self.available & full_mask(Self::NB_PIECES)).map(|n| zobrist::piece_played(n as u8));
+        let hand = self.hand.map(|piece| zobrist::piece_in_hand(piece.number()));
+
+        cells.chain(played).chain(hand).fold(0, |hash, key| hash ^ key)
+    }
+
     #[cfg(test)]
-    pub fn with_scenario(&mut self, scenario: Vec<Move>) {
+    pub fn with_scenario(&mut self, scenario: Vec<GenericMove<W, H, A>>) {
         scenario.into_iter().for_each(|m| {
             self
             .play_and_remove_piece(&m)
@@ -142,136 +239,155 @@ impl Board {
 
     /// Can we play an other turn ?
     pub fn can_play_another_turn(&self) -> bool {
-        self.get_available_pieces().len() > 0
-            || self.get_cells().into_iter().any(|c| c.1.piece.is_none())
+        self.available != 0 && self.occupied != full_mask(Self::NB_CELLS)
     }
 
     /// Play a piece on the board
     /// Piece and cell are identify by their index in the HashMap
-    pub fn play(&mut self, piece: Piece, cell: Cell) -> Result<Piece, ErrorGame> {
+    pub fn play(&mut self, piece: GenericPiece<A>, cell: GenericCell<W, H, A>) -> Result<GenericPiece<A>, ErrorGame> {
         if !self.can_play_another_turn() {
             return Err(ErrorGame::PieceDoesNotBelongPlayable);
         }
 
-        let cell_before_playing = cell;
+        let cell_index = cell.to_index();
+        let cell_before_playing = GenericCell::from_index(self, cell_index)?;
 
-        if let Some(piece) = cell.piece {
-            return Err(ErrorGame::CellIsNotEmpty(cell, piece));
+        if let Some(piece) = cell_before_playing.piece {
+            return Err(ErrorGame::CellIsNotEmpty(cell_index, piece.as_text()));
         }
 
-        let cell_index = cell.to_index();
-        self.cells
-            .entry(cell_index)
-            .and_modify(|f| f.piece = Some(piece));
+        let cell_bit = 1 << cell_index;
+        self.occupied |= cell_bit;
+        self.zobrist ^= zobrist::piece_on_cell(cell_index, piece.number());
+        for (attribute, mask) in self.attributes.iter_mut().enumerate() {
+            if piece.has_attribute(attribute) {
+                *mask |= cell_bit;
+            }
+        }
+        self.cells[cell_index].piece = Some(piece);
 
         trace!(
             "Cell (i = {}) before playing = {} / after playing : {}",
             cell_index,
             cell_before_playing,
-            Cell::from_index(&self, cell_index).unwrap()
+            self[cell_index]
         );
 
         Ok(piece)
     }
+}
 
+impl<const W: usize, const H: usize, const A: usize> GenericBoard<W, H, A> {
     /// Remove the piece from available playable list
-    pub fn remove(&mut self, piece: Piece) -> Result<Piece, ErrorGame> {
-        let index = piece.to_index(&self)?;
+    pub fn remove(&mut self, piece: GenericPiece<A>) -> Result<GenericPiece<A>, ErrorGame> {
+        let index = piece.to_index(self)?;
 
         trace!("Piece {} num {} remove from availables", piece, index);
-        self.available_pieces
-            .remove(&index)
-            .ok_or(ErrorGame::PieceDoesNotExists)
+        self.available &= !(1 << index);
+        self.zobrist ^= zobrist::piece_played(piece.number());
+        if self.hand == Some(piece) {
+            self.set_hand(None);
+        }
+        Ok(piece)
     }
 
     /// The combination of the two previous functions
-    pub fn play_and_remove_piece(&mut self, m: &Move) -> Result<Piece, ErrorGame> {
+    pub fn play_and_remove_piece(&mut self, m: &GenericMove<W, H, A>) -> Result<GenericPiece<A>, ErrorGame> {
         self.play(m.piece(), m.cell())?;
-        Ok(self.remove(m.piece())?)
+        self.remove(m.piece())
+    }
+
+    /// Take back a move: the piece leaves its cell and become available again
+    pub fn unplay(&mut self, m: &GenericMove<W, H, A>) -> Result<GenericPiece<A>, ErrorGame> {
+        let cell_index = m.cell().to_index();
+        let cell = GenericCell::from_index(self, cell_index)?;
+
+        if cell.piece != Some(m.piece()) {
+            return Err(ErrorGame::PieceIsNotOnCell(cell_index, m.piece().as_text()));
+        }
+
+        let cell_bit = 1 << cell_index;
+        self.occupied &= !cell_bit;
+        for mask in self.attributes.iter_mut() {
+            *mask &= !cell_bit;
+        }
+        if !self.is_available(m.piece().number() as usize) {
+            self.available |= 1 << m.piece().number();
+            self.zobrist ^= zobrist::piece_played(m.piece().number());
+        }
+        self.zobrist ^= zobrist::piece_on_cell(cell_index, m.piece().number());
+        self.cells[cell_index].piece = None;
+
+        trace!("Piece {} taken back from cell {}", m.piece(), cell_index);
+        Ok(m.piece())
     }
 
     /// Get the list of available piece that can be played
-    pub fn get_available_pieces(&self) -> BTreeMap<usize, Piece> {
-        self.available_pieces.clone()
+    pub fn get_available_pieces(&self) -> BTreeMap<usize, GenericPiece<A>> {
+        bits(self.available)
+            .map(|n| (n, GenericPiece::from_number(n as u8).unwrap()))
+            .collect()
     }
 
     /// Get the piece from the available stack
-    pub fn get_piece_from_available(&self, index: usize) -> Result<&Piece, ErrorGame> {
-        self.available_pieces
-            .get(&index)
-            .ok_or(ErrorGame::PieceDoesNotBelongPlayable)
+    pub fn get_piece_from_available(&self, index: usize) -> Result<GenericPiece<A>, ErrorGame> {
+        GenericPiece::from_index(self, index)
     }
 
-    pub fn get_cells(&self) -> &BTreeMap<usize, Cell> {
-        &self.cells
+    /// Return if the piece number is still available
+    pub fn is_available(&self, number: usize) -> bool {
+        number < Self::NB_PIECES && self.available & (1 << number) != 0
     }
 
-    /// Return the empty cells available in the board
-    pub fn get_empty_cells(&self) -> BTreeMap<usize, Cell> {
-        self.get_cells()
-            .clone()
-            .into_iter()
-            .filter(|f| f.1.piece.is_none())
-            .collect()
+    pub fn get_cells(&self) -> BTreeMap<usize, GenericCell<W, H, A>> {
+        self.used_cells().iter().enumerate().map(|(i, c)| (i, *c)).collect()
     }
 
-    /// Scan the board and check if a position is winning.
-    /// Return None if no winning position has been found
-    /// Return Some() with the list of winning cells
-    pub fn board_state(&self) -> BoardState {
-        //Horizontal check
-        for i in 0..WIDTH_BOARD {
-            let mut horizontal_cells: Vec<Cell> = Vec::with_capacity(HEIGHT_BOARD);
-            'y_x: for j in 0..HEIGHT_BOARD {
-                //If the cell is empty -> break this loop iteration
-                let current_cell = Cell::from_coordinate(&self, j, i).unwrap();
-                if let None = current_cell.piece {
-                    break 'y_x;
-                }
-                horizontal_cells.push(current_cell);
-            }
+    /// Return the empty cells available in the board
+    pub fn get_empty_cells(&self) -> BTreeMap<usize, GenericCell<W, H, A>> {
+        bits(self.empty_mask()).map(|i| (i, self.cells[i])).collect()
+    }
 
-            if Board::check_cell_is_winning(&mut horizontal_cells) {
-                info!("Horizontal win with cells {:?}", horizontal_cells);
-                return BoardState::Win(self.to_btree(horizontal_cells));
-            }
-        }
+    /// The mask of the cells which already have a piece
+    pub fn occupied_mask(&self) -> u64 {
+        self.occupied
+    }
 
-        for i in 0..WIDTH_BOARD {
-            let mut vertical_cells: Vec<Cell> = Vec::with_capacity(HEIGHT_BOARD);
-            'y_y: for j in 0..HEIGHT_BOARD {
-                //If the cell is empty -> break this loop iteration
-                let current_cell = Cell::from_coordinate(&self, i, j).unwrap();
-                if let None = current_cell.piece {
-                    break 'y_y;
-                }
-                vertical_cells.push(current_cell);
-            }
+    /// The mask of the cells without piece
+    pub fn empty_mask(&self) -> u64 {
+        !self.occupied & full_mask(Self::NB_CELLS)
+    }
 
-            if Board::check_cell_is_winning(&mut vertical_cells) {
-                info!("Vertical win with cells {:?}", vertical_cells);
-                return BoardState::Win(self.to_btree(vertical_cells));
-            }
-        }
+    /// The mask of the cells whose piece has the second value of the attribute (0 = color, 1 = hole, 2 = height, 3 = shape)
+    pub fn attribute_mask(&self, attribute: usize) -> u64 {
+        self.attributes[attribute]
+    }
 
-        //Diagonale
-        let (mut diagonal_cells_top_left_bottom_right, mut diagonal_cells_top_right_bottom_left) =
-            Board::get_diagonal_cells(&self);
+    /// The mask of the piece numbers which have not been played yet
+    pub fn available_mask(&self) -> u64 {
+        self.available
+    }
 
-        if Board::check_cell_is_winning(&mut diagonal_cells_top_left_bottom_right) {
-            info!(
-                "Diagonal win with cells {:?}",
-                diagonal_cells_top_left_bottom_right
-            );
-            return BoardState::Win(self.to_btree(diagonal_cells_top_left_bottom_right));
-        }
+    /// Return if all the cells of the line are filled with pieces sharing at least one attribute
+    pub fn is_winning_line(&self, line: u64) -> bool {
+        self.occupied & line == line
+            && self
+                .attributes
+                .iter()
+                .any(|mask| mask & line == line || mask & line == 0)
+    }
 
-        if Board::check_cell_is_winning(&mut diagonal_cells_top_right_bottom_left) {
-            info!(
-                "Diagonal win with cells {:?}",
-                diagonal_cells_top_right_bottom_left
-            );
-            return BoardState::Win(self.to_btree(diagonal_cells_top_right_bottom_left));
+    /// Scan the board and check if a position is winning.
+    /// Return None if no winning position has been found
+    /// Return Some() with the list of winning cells
+    pub fn board_state(&self) -> BoardState<GenericCell<W, H, A>> {
+        if let Some(line) = self.lines.iter().find(|line| self.is_winning_line(**line)) {
+            info!("Win with cells {:b}", line);
+            let cells = self.mask_to_btree(*line);
+            if self.rules.misere() {
+                return BoardState::Loss(cells);
+            }
+            return BoardState::Win(cells);
         }
 
         // No win condition, the game continue. If we don't have any other piece to play, it's a draw
@@ -281,47 +397,70 @@ impl Board {
         BoardState::GameInProgress
     }
 
-    /// Do the reverse mapping by filtering  the original BTree from the Vec in parameter
-    pub fn to_btree(&self, v: Vec<Cell>) -> BTreeMap<usize, Cell> {
-        self.cells
-            .clone()
-            .into_iter()
-            .filter(|&(_, c)| v.contains(&c))
+    /// Return every winning line of the board, with their cells indexed by their position
+    pub fn winning_lines(&self) -> Vec<BTreeMap<usize, GenericCell<W, H, A>>> {
+        self.lines
+            .iter()
+            .filter(|line| self.is_winning_line(**line))
+            .map(|line| self.mask_to_btree(*line))
             .collect()
     }
 
-    /// Return if the current cells are a winning position
-    pub fn check_cell_is_winning(cells: &mut Vec<Cell>) -> bool {
-        if !cells.into_iter().all(|f| f.piece.is_some()) {
-            trace!("check_cell_is_winning : some cells are empty");
-            return false;
-        }
-
-        let mut pieces: Vec<Piece> = cells.into_iter().map(|c| c.piece.unwrap()).collect();
-        let is_win = Piece::check_piece_is_winning(&mut pieces);
+    /// Return the winning lines which go through the cell, as cell masks
+    pub fn completed_lines(&self, index: usize) -> Vec<u64> {
+        self.lines
+            .iter()
+            .copied()
+            .filter(|line| line & (1 << index) != 0 && self.is_winning_line(*line))
+            .collect()
+    }
 
-        is_win
+    /// The mask of the available piece numbers which would complete a line if they were given now:
+    /// the pieces sharing an attribute value with all the pieces of a line where only one cell is empty
+    pub fn winning_pieces_mask(&self) -> u64 {
+        self.lines
+            .iter()
+            .filter(|line| (self.occupied & *line).count_ones() == line.count_ones() - 1)
+            .flat_map(|line| {
+                let occupied = self.occupied & line;
+                (0..A).map(move |attribute| {
+                    let with_attribute = GenericPiece::<A>::numbers_with_attribute(attribute);
+                    match (self.attributes[attribute] & occupied).count_ones() {
+                        0 => !with_attribute,
+                        nb_set if nb_set == occupied.count_ones() => with_attribute,
+                        _ => 0,
+                    }
+                })
+            })
+            .fold(0, |pieces, attribute_pieces| pieces | attribute_pieces)
+            & self.available
     }
 
-    /// Return the list of the immediate available move from the current board
-    pub fn get_available_moves(&self) -> Vec<Move> {
-        let mut available_next_move: Vec<Move> = vec![];
+    /// Return the cells of the mask, indexed by their position
+    pub fn mask_to_btree(&self, mask: u64) -> BTreeMap<usize, GenericCell<W, H, A>> {
+        bits(mask & full_mask(Self::NB_CELLS)).map(|i| (i, self.cells[i])).collect()
+    }
 
-        for (_, piece) in &self.get_available_pieces() {
-            for (_, cell) in &self.get_empty_cells() {
-                available_next_move.push(Move::new(*piece, *cell));
-            }
-        }
+    /// Do the reverse mapping by filtering  the original BTree from the Vec in parameter
+    pub fn to_btree(&self, v: Vec<GenericCell<W, H, A>>) -> BTreeMap<usize, GenericCell<W, H, A>> {
+        self.get_cells()
+            .into_iter()
+            .filter(|&(_, c)| v.contains(&c))
+            .collect()
+    }
 
-        available_next_move
+    /// Return the list of the immediate available move from the current board
+    pub fn get_available_moves(&self) -> Vec<GenericMove<W, H, A>> {
+        bits(self.available)
+            .flat_map(|n| self.get_available_moves_from_piece(GenericPiece::from_number(n as u8).unwrap()))
+            .collect()
     }
 
     /// Return the list of the immediate available move from the current board
-    pub fn get_available_moves_from_piece(&self, piece: Piece) -> Vec<Move> {
-        self.get_empty_cells()
-            .into_iter()
-            .map(|(index_cell, _)| Move::new(piece, Cell::from_index(&self, index_cell).unwrap()))
-            .collect::<Vec<Move>>()
+    pub fn get_available_moves_from_piece(&self, piece: GenericPiece<A>) -> Vec<GenericMove<W, H, A>> {
+        bits(self.empty_mask())
+            .map(|i| GenericMove::new(piece, self.cells[i]))
+            .collect()
     }
 
     // #[cfg(target_arch = "wasm32")]
@@ -336,32 +475,22 @@ impl Board {
         use prettytable::{Cell as pCell, Row as pRow, Table as pTable};
         use ansi_term::Style;
 
-        let pieces_feature = Color::to_vec_boxed()
-            .into_iter()
-            .map(|p| p)
-            .chain(Hole::to_vec_boxed().into_iter().map(|p| p))
-            .chain(Height::to_vec_boxed().into_iter().map(|p| p))
-            .chain(Shape::to_vec_boxed().into_iter().map(|p| p));
-
         let mut legend = format!(
-            "{}{}{}",
-            "\n",
+            "\n{}\n",
             Style::new().bold().underline().paint("Legend:"),
-            "\n"
         );
 
         //Draw legend
-        for (i, e) in pieces_feature.into_iter().enumerate() {
-            legend = format!(
-                "{} \t {}: {}",
-                legend,
-                e.color()[i % 2].paint(e.acronym()),
-                e.name()
-            );
-
-            if (i + 1) % 2 == 0 {
-                legend = format!("{}\n", legend);
+        for attribute in 0..A {
+            for value in [false, true] {
+                legend = format!(
+                    "{} \t {}: {}",
+                    legend,
+                    GenericPiece::<A>::colored_acronym(attribute, value),
+                    GenericPiece::<A>::attribute_name(attribute, value)
+                );
             }
+            legend = format!("{}\n", legend);
         }
         //Draw available piece
         legend = format!(
@@ -372,12 +501,13 @@ impl Board {
         let mut table_available_piece = pTable::new();
         let mut current_row = pRow::empty();
 
-        for (index, piece) in self.available_pieces.iter().enumerate() {
+        let available_pieces = self.get_available_pieces();
+        for (index, piece) in available_pieces.iter().enumerate() {
             current_row.add_cell(pCell::new_align(
                 format!("{:0>2}\n{}", piece.0 + 1, piece.1.to_string().as_str()).as_str(),
                 prettytable::format::Alignment::CENTER,
             ));
-            if (index + 1) % 8 == 0 && index != self.available_pieces.len() - 1 {
+            if (index + 1) % 8 == 0 && index != available_pieces.len() - 1 {
                 table_available_piece.add_row(current_row);
                 current_row = pRow::empty();
             }
@@ -387,14 +517,14 @@ impl Board {
         //Draw Board
         let mut table_board = pTable::new();
         current_row = pRow::empty();
-        for (i, cell) in self.cells.iter() {
+        for (i, cell) in self.used_cells().iter().enumerate() {
             let draw_cell = pCell::new_align(
                 format!("{:0>2}\n{}", i + 1, cell.to_string().as_str()).as_str(),
                 prettytable::format::Alignment::CENTER,
             );
 
             current_row.add_cell(draw_cell);
-            if (i + 1) % WIDTH_BOARD == 0 {
+            if (i + 1) % W == 0 {
                 table_board.add_row(current_row);
                 current_row = pRow::empty();
             }
@@ -404,77 +534,288 @@ impl Board {
     }
 }
 
-/// Give access to cells directly from Board (board[0], board[10])
-impl Index<usize> for Board {
-    type Output = Cell;
+impl Board {
+    /// Return if the current cells are a winning position
+    pub fn check_cell_is_winning(cells: &mut [Cell]) -> bool {
+        if !cells.iter().all(|f| f.piece.is_some()) {
+            trace!("check_cell_is_winning : some cells are empty");
+            return false;
+        }
 
-    fn index(&self, index: usize) -> &Self::Output {
-        assert!(index <= WIDTH_BOARD * HEIGHT_BOARD, "Index out of bounds");
-        self.cells.get(&index).unwrap()
+        let mut pieces: Vec<Piece> = cells.iter().map(|c| c.piece.unwrap()).collect();
+        Piece::check_piece_is_winning(&mut pieces)
+    }
+}
+
+impl<const W: usize, const H: usize, const A: usize> GenericBoard<W, H, A> {
+    /// Write the position on one line: the rows from the top separated by '/' (each cell is the text of its piece or '.'),
+    /// then the piece in hand ('-' if none) and the player who places the next piece (1 or 2).
+    /// For instance "DEXC.../..WFTS./..../.... DFTS 1"
+    pub fn to_notation(&self) -> String {
+        let rows: Vec<String> = self
+            .used_cells()
+            .chunks(W)
+            .map(|row| {
+                row.iter()
+                    .map(|cell| cell.piece.map_or(".".to_string(), |piece| piece.as_text()))
+                    .collect()
+            })
+            .collect();
+
+        format!(
+            "{} {} {}",
+            rows.join("/"),
+            self.hand.map_or("-".to_string(), |piece| piece.as_text()),
+            self.player_to_place()
+        )
+    }
+
+    /// Read a position written with `to_notation`, with the classic rules
+    pub fn from_notation(notation: &str) -> Result<Self, ErrorGame> {
+        Self::from_notation_with_rules(notation, Rules::default())
+    }
+
+    /// Read a position written with `to_notation`.
+    /// The pieces which are not on the board are the available ones
+    pub fn from_notation_with_rules(notation: &str, rules: Rules) -> Result<Self, ErrorGame> {
+        let invalid = |reason: String| ErrorGame::InvalidNotation(reason);
+        let illegal = |reason: String| ErrorGame::IllegalPosition(reason);
+
+        let parts: Vec<&str> = notation.split_whitespace().collect();
+        let [rows, hand, player] = parts[..] else {
+            return Err(invalid("expected the rows, the piece in hand and the player".to_string()));
+        };
+
+        let rows: Vec<&str> = rows.split('/').collect();
+        if rows.len() != H {
+            return Err(invalid(format!("expected {} rows, found {}", H, rows.len())));
+        }
+
+        let mut board = Self::create_with_rules(rules);
+        for (y, row) in rows.into_iter().enumerate() {
+            let slots = Self::read_row(row)?;
+            if slots.len() != W {
+                return Err(invalid(format!("expected {} cells in the row {}, found {}", W, y + 1, slots.len())));
+            }
+
+            for (x, piece) in slots.into_iter().enumerate() {
+                let Some(piece) = piece else { continue };
+                if !board.is_available(piece.number() as usize) {
+                    return Err(illegal(format!("the piece {} is on the board twice", piece.as_text())));
+                }
+                board.play(piece, GenericCell::new(Self::coordinate_to_index(x, y)?)?)?;
+                board.remove(piece)?;
+            }
+        }
+
+        if hand != "-" {
+            let piece = Self::read_piece(hand)?;
+            if !board.is_available(piece.number() as usize) {
+                return Err(illegal(format!("the piece in hand {} is already on the board", hand)));
+            }
+            board.set_hand(Some(piece));
+        }
+
+        if player != board.player_to_place().to_string() {
+            return Err(illegal(format!(
+                "with {} pieces on the board, the player {} has to place the next piece",
+                board.occupied.count_ones(),
+                board.player_to_place()
+            )));
+        }
+
+        Ok(board)
+    }
+
+    /// The player (1 or 2) who places the next piece: the first player places when an even number of pieces are on the board
+    fn player_to_place(&self) -> usize {
+        self.occupied.count_ones() as usize % 2 + 1
+    }
+
+    /// Read the cells of a row: '.' for an empty cell, the text of the piece otherwise
+    fn read_row(row: &str) -> Result<Vec<Option<GenericPiece<A>>>, ErrorGame> {
+        let chars: Vec<char> = row.chars().collect();
+        let mut slots = vec![];
+        let mut i = 0;
+        while i < chars.len() {
+            if chars[i] == '.' {
+                slots.push(None);
+                i += 1;
+            } else {
+                let text: String = chars[i..chars.len().min(i + A)].iter().collect();
+                slots.push(Some(Self::read_piece(&text)?));
+                i += A;
+            }
+        }
+        Ok(slots)
+    }
+
+    fn read_piece(text: &str) -> Result<GenericPiece<A>, ErrorGame> {
+        GenericPiece::from_text(text)
     }
 }
 
-impl IndexMut<usize> for Board {
-    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        assert!(index <= WIDTH_BOARD * HEIGHT_BOARD, "Index out of bounds");
-        self.cells.get_mut(&index).unwrap()
+/// The serialized board: the piece of each cell and the pieces which have not been played yet.
+/// The masks and the lines are computed back from them
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedBoard<const A: usize> {
+    rules: Rules,
+    cells: Vec<Option<GenericPiece<A>>>,
+    available: Vec<GenericPiece<A>>,
+    #[serde(default)]
+    hand: Option<GenericPiece<A>>,
+}
+
+#[cfg(feature = "serde")]
+impl<const W: usize, const H: usize, const A: usize> serde::Serialize for GenericBoard<W, H, A> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedBoard {
+            rules: self.rules,
+            cells: self.used_cells().iter().map(|cell| cell.piece).collect(),
+            available: self.get_available_pieces().into_values().collect(),
+            hand: self.hand,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const W: usize, const H: usize, const A: usize> serde::Deserialize<'de> for GenericBoard<W, H, A> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let serialized = SerializedBoard::<A>::deserialize(deserializer)?;
+        if serialized.cells.len() != Self::NB_CELLS {
+            return Err(D::Error::invalid_length(serialized.cells.len(), &"one piece or none per cell"));
+        }
+
+        let mut board = Self::create_with_rules(serialized.rules);
+        let mut placed: u64 = 0;
+        for (index, piece) in serialized.cells.into_iter().enumerate() {
+            if let Some(piece) = piece {
+                if placed & (1 << piece.number()) != 0 {
+                    return Err(D::Error::custom(format!("the piece {} is placed twice", piece.as_text())));
+                }
+                placed |= 1 << piece.number();
+                board.play(piece, GenericCell::new(index).map_err(D::Error::custom)?).map_err(D::Error::custom)?;
+            }
+        }
+
+        // Each piece is either on the board or still available, and the piece in hand hasn't been placed yet
+        let available = serialized
+            .available
+            .iter()
+            .fold(0, |available, piece| available | (1 << piece.number()));
+        if available & placed != 0 {
+            return Err(D::Error::custom("a piece on the board is also available"));
+        }
+        if available | placed != full_mask(Self::NB_PIECES) {
+            return Err(D::Error::custom("a piece is neither on the board nor available"));
+        }
+        if serialized.hand.is_some_and(|piece| available & (1 << piece.number()) == 0) {
+            return Err(D::Error::custom("the piece in hand is not available"));
+        }
+        board.available = available;
+        board.hand = serialized.hand;
+        board.zobrist = board.compute_zobrist();
+
+        Ok(board)
+    }
+}
+
+/// The equal boards have the same Zobrist hash
+impl<const W: usize, const H: usize, const A: usize> Hash for GenericBoard<W, H, A> {
+    fn hash<S: std::hash::Hasher>(&self, state: &mut S) {
+        self.zobrist.hash(state);
+    }
+}
+
+/// Give access to cells directly from Board (board[0], board[10])
+impl<const W: usize, const H: usize, const A: usize> Index<usize> for GenericBoard<W, H, A> {
+    type Output = GenericCell<W, H, A>;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        assert!(index < Self::NB_CELLS, "Index out of bounds");
+        &self.cells[index]
     }
 }
 
 /// Draw the board
-impl Display for Board {
+impl<const W: usize, const H: usize, const A: usize> Display for GenericBoard<W, H, A> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let legend = self.display_board();
-        return write!(f, "{}", legend);
+        write!(f, "{}", legend)
     }
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
-pub struct Cell {
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct GenericCell<const W: usize, const H: usize, const A: usize> {
     /// Determine if a piece is present on the cell or not
-    piece: Option<Piece>,
-    /// The horizontal index
-    x: usize,
+    piece: Option<GenericPiece<A>>,
+    /// The horizontal index (a board has at most 64 cells, so it fits in a byte)
+    x: u8,
     /// The vertical index
-    y: usize,
+    y: u8,
 }
 
-impl Cell {
+impl<const W: usize, const H: usize, const A: usize> GenericCell<W, H, A> {
     /// Create a new Cell
-    pub fn new(index: usize) -> Result<Cell, ErrorGame> {
-        let (x, y) = Board::index_to_coordinate(index)?;
-        Ok(Cell { piece: None, x, y })
+    pub fn new(index: usize) -> Result<Self, ErrorGame> {
+        let (x, y) = GenericBoard::<W, H, A>::index_to_coordinate(index)?;
+        Ok(GenericCell {
+            piece: None,
+            x: x as u8,
+            y: y as u8,
+        })
     }
 
     pub fn to_index(&self) -> usize {
-        self.x + self.y * HEIGHT_BOARD
+        self.x as usize + self.y as usize * W
     }
 
     pub fn to_coordinate(&self) -> (usize, usize) {
-        (self.x, self.y)
+        (self.x as usize, self.y as usize)
     }
 
-    pub fn from_index(board: &Board, index: usize) -> Result<Self, ErrorGame> {
-        Ok(*board
-            .get_cells()
-            .get(&index)
-            .ok_or(ErrorGame::IndexOutOfBound)?)
+    pub fn from_index(board: &GenericBoard<W, H, A>, index: usize) -> Result<Self, ErrorGame> {
+        if index >= GenericBoard::<W, H, A>::NB_CELLS {
+            return Err(ErrorGame::IndexOutOfBound);
+        }
+        Ok(board[index])
     }
 
-    pub fn from_coordinate(board: &Board, x: usize, y: usize) -> Result<Self, ErrorGame> {
-        Ok(*board
-            .get_cells()
-            .get(&Board::coordinate_to_index(x, y)?)
-            .unwrap())
+    pub fn from_coordinate(board: &GenericBoard<W, H, A>, x: usize, y: usize) -> Result<Self, ErrorGame> {
+        Ok(board[GenericBoard::<W, H, A>::coordinate_to_index(x, y)?])
     }
 
-    pub fn piece(&self) -> Option<Piece> {
+    pub fn piece(&self) -> Option<GenericPiece<A>> {
         self.piece
     }
 }
 
+/// A cell is serialized as its index and its piece
+#[cfg(feature = "serde")]
+impl<const W: usize, const H: usize, const A: usize> serde::Serialize for GenericCell<W, H, A> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (self.to_index(), self.piece).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const W: usize, const H: usize, const A: usize> serde::Deserialize<'de> for GenericCell<W, H, A> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let (index, piece) = <(usize, Option<GenericPiece<A>>)>::deserialize(deserializer)?;
+        let mut cell = GenericCell::new(index).map_err(D::Error::custom)?;
+        cell.piece = piece;
+        Ok(cell)
+    }
+}
+
 /// Draw a cell
-impl Display for Cell {
+impl<const W: usize, const H: usize, const A: usize> Display for GenericCell<W, H, A> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
@@ -482,7 +823,7 @@ impl Display for Cell {
             if let Some(p) = &self.piece {
                 p.to_string()
             } else {
-                "    ".to_string()
+                " ".repeat(A)
             }
         )
     }
@@ -491,6 +832,8 @@ impl Display for Cell {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::ErrorKind;
+    use crate::r#move::Move;
 
     #[test]
     fn test_index_and_coordinate() {
@@ -601,13 +944,15 @@ mod tests {
     /// Quarto game has 16 piece
     #[test]
     fn test_create_all_pieces_should_has_16_elements() {
-        assert_eq!(Board::generate_all_pieces().len(), 16);
+        let pieces = Board::create().get_available_pieces();
+        assert_eq!(pieces.len(), 16);
+        assert_eq!(pieces.len(), WIDTH_BOARD * HEIGHT_BOARD);
     }
 
     /// All pieces should be unique
     #[test]
     fn test_all_pieces_should_be_unique() {
-        let pieces = Board::generate_all_pieces();
+        let pieces = Board::create().get_available_pieces();
 
         for current_piece in pieces.iter() {
             assert_eq!(pieces.iter().filter(|p| *p == current_piece).count(), 1);
@@ -617,16 +962,20 @@ mod tests {
     #[test]
     /// When create a new game, we need to have 16 cells
     fn test_create_new_board_should_have_16_cells() {
-        assert_eq!(Board::generate_all_cells().len(), 16);
-        assert_eq!(Board::create().cells.len(), 16);
+        assert!(Board::generate_all_cells()[..16]
+            .iter()
+            .enumerate()
+            .all(|(i, cell)| cell.to_index() == i));
+        assert_eq!(Board::create().used_cells().len(), 16);
         assert_eq!(Board::create().get_cells().len(), 16);
     }
 
     #[test]
     /// When create a new game, all cells must be empty
+    #[allow(clippy::bool_assert_comparison)]
     fn test_create_new_board_should_be_empty() {
         assert_eq!(
-            Board::create().cells.iter().all(|p| p.1.piece.is_none()),
+            Board::create().used_cells().iter().all(|c| c.piece.is_none()),
             true
         );
     }
@@ -634,7 +983,7 @@ mod tests {
     /// When create a new game, all piece should be available to be played
     #[test]
     fn test_create_new_board_all_piece_should_be_available() {
-        assert_eq!(Board::create().available_pieces.len(), 16);
+        assert_eq!(Board::create().available.count_ones(), 16);
     }
 
     #[test]
@@ -652,7 +1001,8 @@ mod tests {
             .unwrap();
 
         //Should haven't none in the first cell after play
-        let cell = board.get_cells().get(&INDEX_CELL).unwrap();
+        let cells = board.get_cells();
+        let cell = cells.get(&INDEX_CELL).unwrap();
         assert_ne!(cell.piece, None);
 
         assert_ne!(board[INDEX_CELL].piece, None);
@@ -686,16 +1036,128 @@ mod tests {
             .unwrap();
 
         //Piece is not playable anymore
-        assert_eq!(board.available_pieces.get(&INDEX_PIECE), None);
+        assert!(!board.is_available(INDEX_PIECE));
 
         //And if you try to access, you got a PieceDoesNotBelongPlayable error
         let error_expected = Err(ErrorGame::PieceDoesNotBelongPlayable);
         assert_eq!(Piece::from_index(&board, INDEX_PIECE), error_expected);
     }
 
+    #[test]
+    fn test_play_on_occupied_cell_with_stale_cell_should_fail() {
+        let mut board = Board::create();
+        let stale_cell = Cell::from_index(&board, 5).unwrap();
+
+        board.play(Piece::from("DEXC"), stale_cell).unwrap();
+        assert_eq!(
+            board.play(Piece::from("WETS"), stale_cell),
+            Err(ErrorGame::CellIsNotEmpty(5, "DEXC".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_masks_follow_played_pieces() {
+        let mut board = Board::create();
+        board
+            .play_and_remove_piece(&Move::new(Piece::from("DEXS"), Cell::from_index(&board, 3).unwrap()))
+            .unwrap();
+
+        assert_eq!(board.occupied_mask(), 0b1000);
+        assert_eq!(board.attribute_mask(0), 0b1000);
+        assert_eq!(board.attribute_mask(1), 0);
+        assert_eq!(board.attribute_mask(2), 0);
+        assert_eq!(board.attribute_mask(3), 0b1000);
+        assert!(!board.is_available(Piece::from("DEXS").number() as usize));
+        assert_eq!(board.get_available_moves().len(), 15 * 15);
+    }
+
+    #[test]
+    fn test_winning_lines_should_return_all_lines() {
+        let mut board = Board::create();
+        for (piece, cell) in [
+            ("DEXC", 0),
+            ("DFXC", 1),
+            ("DETC", 2),
+            ("DFTC", 3),
+            ("DEXS", 4),
+            ("DETS", 8),
+            ("DFXS", 12),
+        ] {
+            board
+                .play_and_remove_piece(&Move::new(Piece::from(piece), Cell::from_index(&board, cell).unwrap()))
+                .unwrap();
+        }
+
+        let lines = board.winning_lines();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].keys().copied().collect::<Vec<usize>>(), vec![0, 1, 2, 3]);
+        assert_eq!(lines[1].keys().copied().collect::<Vec<usize>>(), vec![0, 4, 8, 12]);
+    }
+
+    #[test]
+    fn test_winning_pieces_mask() {
+        let mut board = Board::create();
+        assert_eq!(board.winning_pieces_mask(), 0);
+
+        board.with_scenario(vec![
+            Move::new(Piece::from("DEXC"), Cell::from_index(&board, 0).unwrap()),
+            Move::new(Piece::from("DFXC"), Cell::from_index(&board, 1).unwrap()),
+            Move::new(Piece::from("DETC"), Cell::from_index(&board, 2).unwrap()),
+        ]);
+
+        // The dark or circle pieces left complete the first row
+        let winning = board.winning_pieces_mask();
+        assert_eq!(winning.count_ones(), 9);
+        assert_ne!(winning & (1 << Piece::from("DFTS").number()), 0);
+        assert_ne!(winning & (1 << Piece::from("WFTC").number()), 0);
+        assert_eq!(winning & (1 << Piece::from("WFTS").number()), 0);
+        assert_eq!(winning & (1 << Piece::from("DEXC").number()), 0);
+    }
+
+    #[test]
+    fn test_broken_diagonal_win_only_with_toroidal_rules() {
+        let scenario = [("DEXC", 1), ("DFXC", 6), ("DETC", 11), ("DFTC", 12)];
+        let mut classic_board = Board::create();
+        let mut toroidal_board = Board::create_with_rules(Rules::classic().with_toroidal(true));
+
+        for board in [&mut classic_board, &mut toroidal_board] {
+            for (piece, cell) in scenario {
+                board
+                    .play_and_remove_piece(&Move::new(Piece::from(piece), Cell::from_index(board, cell).unwrap()))
+                    .unwrap();
+            }
+        }
+
+        assert_eq!(classic_board.board_state(), BoardState::GameInProgress);
+        match toroidal_board.board_state() {
+            BoardState::Win(cells) => {
+                assert_eq!(cells.keys().copied().collect::<Vec<usize>>(), vec![1, 6, 11, 12])
+            }
+            state => panic!("The broken diagonal should win, got {:?}", state),
+        }
+    }
+
+    #[test]
+    fn test_unplay_should_restore_board() {
+        let mut board = Board::create();
+        let initial_board = board.clone();
+        let m = Move::new(Piece::from("WFTC"), Cell::from_index(&board, 6).unwrap());
+
+        board.play_and_remove_piece(&m).unwrap();
+        assert_eq!(board.unplay(&m).unwrap(), Piece::from("WFTC"));
+        assert!(board == initial_board);
+
+        // Nothing to take back anymore
+        assert_eq!(
+            board.unplay(&m),
+            Err(ErrorGame::PieceIsNotOnCell(6, "WFTC".to_string()))
+        );
+    }
+
     ///Index board accessor out of range
     #[test]
     #[should_panic]
+    #[allow(clippy::unnecessary_operation)]
     fn test_access_index_board_out_of_bounds_should_panic() {
         Board::create()[20];
     }
@@ -745,7 +1207,7 @@ mod tests {
 
         let mut btree_win: BTreeMap<usize, Cell> = BTreeMap::new();
         for play in &plays {
-            btree_win.insert(play.1, *board.cells.get(&play.1).unwrap());
+            btree_win.insert(play.1, board.cells[play.1]);
         }
 
         let maybe_cell_winning = board.board_state();
@@ -769,7 +1231,7 @@ mod tests {
         }
 
         for play in &plays {
-            btree_win.insert(play.1, *board.cells.get(&play.1).unwrap());
+            btree_win.insert(play.1, board.cells[play.1]);
         }
 
         let maybe_cell_winning = board.board_state();
@@ -795,7 +1257,7 @@ mod tests {
         }
 
         for play in &plays {
-            btree_win.insert(play.1, *board.cells.get(&play.1).unwrap());
+            btree_win.insert(play.1, board.cells[play.1]);
         }
 
         debug!("{}", board);
@@ -891,6 +1353,237 @@ mod tests {
         assert_eq!(maybe_cell_winning, BoardState::Draw);
     }
 
+    #[test]
+    fn test_small_board_with_three_attributes() {
+        let mut board = GenericBoard::<3, 3, 3>::create();
+        assert_eq!(board.get_available_pieces().len(), 8);
+        assert_eq!(board.get_cells().len(), 9);
+        assert_eq!(board.lines().len(), 8);
+
+        // Three pieces with the first attribute in the middle row
+        for (cell, number) in [(3, 0b001), (4, 0b011), (5, 0b101)] {
+            let piece = GenericPiece::<3>::from_number(number).unwrap();
+            board.play(piece, GenericCell::from_index(&board, cell).unwrap()).unwrap();
+            board.remove(piece).unwrap();
+        }
+
+        assert_eq!(
+            board.board_state(),
+            BoardState::Win(board.mask_to_btree(0b000_111_000))
+        );
+    }
+
+    #[test]
+    fn test_large_board_with_five_attributes() {
+        let mut board = GenericBoard::<5, 5, 5>::create();
+        assert_eq!(board.get_available_pieces().len(), 32);
+        assert_eq!(board.get_cells().len(), 25);
+        assert_eq!(board.lines().len(), 12);
+
+        // Four pieces sharing an attribute are not enough on a line of five cells
+        for (cell, number) in [(0, 0b10000), (5, 0b10001), (10, 0b10010), (15, 0b10100)] {
+            let piece = GenericPiece::<5>::from_number(number).unwrap();
+            board.play(piece, GenericCell::from_index(&board, cell).unwrap()).unwrap();
+            board.remove(piece).unwrap();
+        }
+        assert_eq!(board.board_state(), BoardState::GameInProgress);
+
+        let piece = GenericPiece::<5>::from_number(0b11000).unwrap();
+        board.play(piece, GenericCell::from_index(&board, 20).unwrap()).unwrap();
+        board.remove(piece).unwrap();
+        assert_eq!(board.winning_lines(), vec![board.mask_to_btree(0x0010_8421)]);
+    }
+
+    #[test]
+    fn test_large_board_is_full_before_running_out_of_pieces() {
+        let mut board = GenericBoard::<5, 5, 5>::create();
+        for cell in 0..25 {
+            let piece = *board.get_available_pieces().values().next().unwrap();
+            board.play(piece, GenericCell::from_index(&board, cell).unwrap()).unwrap();
+            board.remove(piece).unwrap();
+        }
+
+        assert_eq!(board.get_available_pieces().len(), 7);
+        assert!(!board.can_play_another_turn());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_board() {
+        let mut board = Board::create_with_rules(Rules::advanced());
+        board.with_scenario(vec![
+            Move::new(Piece::from("DEXC"), Cell::from_index(&board, 0).unwrap()),
+            Move::new(Piece::from("WFTS"), Cell::from_index(&board, 5).unwrap()),
+        ]);
+
+        let json = serde_json::to_string(&board).unwrap();
+        assert!(json.starts_with(r#"{"rules":{"square_wins":true"#));
+        assert!(json.contains(r#""cells":["DEXC",null,null,null,null,"WFTS""#));
+        let restored: Board = serde_json::from_str(&json).unwrap();
+        assert!(restored == board);
+
+        let bytes = bincode::serialize(&board).unwrap();
+        assert!(bincode::deserialize::<Board>(&bytes).unwrap() == board);
+
+        // A board of another size can not be read
+        assert!(serde_json::from_str::<GenericBoard<3, 3, 3>>(&json).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_board_with_inconsistent_pieces() {
+        let mut board = Board::create();
+        board
+            .play_and_remove_piece(&Move::new(Piece::from("DEXC"), Cell::from_index(&board, 0).unwrap()))
+            .unwrap();
+        let json = serde_json::to_value(&board).unwrap();
+        let read = |change: &dyn Fn(&mut serde_json::Value)| {
+            let mut json = json.clone();
+            change(&mut json);
+            serde_json::from_value::<Board>(json)
+        };
+
+        let mut hand = board.clone();
+        hand.set_hand(Some(Piece::from("WEXC")));
+        assert!(read(&|json| json["hand"] = "WEXC".into()).unwrap() == hand);
+
+        // A piece on the board can't be played again
+        assert!(read(&|json| json["available"].as_array_mut().unwrap().push("DEXC".into())).is_err());
+        assert!(read(&|json| json["cells"][1] = "DEXC".into()).is_err());
+        // Every piece is somewhere
+        assert!(read(&|json| {
+            json["available"].as_array_mut().unwrap().pop();
+        })
+        .is_err());
+        // The piece in hand hasn't been placed
+        assert!(read(&|json| json["hand"] = "DEXC".into()).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_cell_and_board_state() {
+        let mut board = Board::create();
+        board.play(Piece::from("DEXC"), Cell::from_index(&board, 3).unwrap()).unwrap();
+
+        let json = serde_json::to_string(&board[3]).unwrap();
+        assert_eq!(json, r#"[3,"DEXC"]"#);
+        assert_eq!(serde_json::from_str::<Cell>(&json).unwrap(), board[3]);
+        assert!(serde_json::from_str::<Cell>(r#"[16,null]"#).is_err());
+
+        for state in [
+            BoardState::GameInProgress,
+            BoardState::Win(board.mask_to_btree(0x000F)),
+            BoardState::Draw,
+        ] {
+            let json = serde_json::to_string(&state).unwrap();
+            assert_eq!(serde_json::from_str::<BoardState>(&json).unwrap(), state);
+        }
+    }
+
+    #[test]
+    fn test_notation() {
+        let mut board = Board::create();
+        assert_eq!(board.to_notation(), "..../..../..../.... - 1");
+
+        board.with_scenario(vec![
+            Move::new(Piece::from("DEXC"), Cell::from_index(&board, 0).unwrap()),
+            Move::new(Piece::from("WFTS"), Cell::from_index(&board, 6).unwrap()),
+            Move::new(Piece::from("DFTC"), Cell::from_index(&board, 15).unwrap()),
+        ]);
+        board.set_hand(Some(Piece::from("WEXS")));
+
+        let notation = board.to_notation();
+        assert_eq!(notation, "DEXC.../..WFTS./..../...DFTC WEXS 2");
+
+        let restored = Board::from_notation(&notation).unwrap();
+        assert!(restored == board);
+        assert_eq!(restored.get_available_pieces().len(), 13);
+        assert_eq!(restored.hand(), Some(Piece::from("WEXS")));
+        assert_eq!(restored.to_notation(), notation);
+
+        let advanced = Board::from_notation_with_rules(&notation, Rules::advanced()).unwrap();
+        assert_eq!(advanced.rules(), Rules::advanced());
+
+        let small = GenericBoard::<3, 3, 3>::from_notation("DET../.WFT./... - 1").unwrap();
+        assert_eq!(small.to_notation(), "DET../.WFT./... - 1");
+    }
+
+    #[test]
+    fn test_invalid_notation() {
+        let kind = |notation: &str| Board::from_notation(notation).err().map(|e| e.kind());
+        let invalid = |notation: &str| kind(notation) == Some(ErrorKind::InvalidNotation);
+        let illegal = |notation: &str| kind(notation) == Some(ErrorKind::IllegalPosition);
+
+        // Missing parts, rows or cells
+        assert!(invalid("..../..../..../...."));
+        assert!(invalid("..../..../.... - 1"));
+        assert!(invalid("..../..../..../... - 1"));
+        assert!(invalid("DEXC..../..../..../.... - 1"));
+        // Unknown pieces
+        assert!(invalid("DEX.../..../..../.... - 2"));
+        assert!(invalid("..../..../..../.... ZZZZ 1"));
+        // The same piece twice, on the board or in hand
+        assert!(illegal("DEXCDEXC../..../..../.... - 1"));
+        assert!(illegal("DEXC.../..../..../.... DEXC 2"));
+        // The player doesn't match the number of pieces on the board
+        assert!(illegal("DEXC.../..../..../.... - 1"));
+        assert!(illegal("..../..../..../.... - 2"));
+    }
+
+    #[test]
+    fn test_zobrist_is_updated_on_each_move() {
+        let mut board = Board::create();
+        assert_eq!(board.zobrist(), 0);
+
+        let first = Move::new(Piece::from("DEXC"), Cell::from_index(&board, 0).unwrap());
+        let second = Move::new(Piece::from("WFTS"), Cell::from_index(&board, 6).unwrap());
+        board.with_scenario(vec![first, second]);
+        assert_ne!(board.zobrist(), 0);
+        assert_eq!(board.zobrist(), board.compute_zobrist());
+
+        // The same position reached in another order has the same hash
+        let mut other = Board::create();
+        other.with_scenario(vec![
+            Move::new(Piece::from("WFTS"), Cell::from_index(&other, 6).unwrap()),
+            Move::new(Piece::from("DEXC"), Cell::from_index(&other, 0).unwrap()),
+        ]);
+        assert_eq!(other.zobrist(), board.zobrist());
+
+        // The piece in hand changes the hash, until it is played
+        let before_hand = board.zobrist();
+        board.set_hand(Some(Piece::from("DFTC")));
+        assert_ne!(board.zobrist(), before_hand);
+        assert_eq!(board.zobrist(), board.compute_zobrist());
+        board
+            .play_and_remove_piece(&Move::new(Piece::from("DFTC"), Cell::from_index(&board, 15).unwrap()))
+            .unwrap();
+        assert_eq!(board.hand(), None);
+        assert_eq!(board.zobrist(), board.compute_zobrist());
+
+        // Taking back the moves gives the hash back
+        board.unplay(&Move::new(Piece::from("DFTC"), Cell::from_index(&board, 15).unwrap())).unwrap();
+        assert_eq!(board.zobrist(), before_hand);
+        board.unplay(&second).unwrap();
+        board.unplay(&first).unwrap();
+        assert_eq!(board.zobrist(), 0);
+    }
+
+    #[test]
+    fn test_hash_positions() {
+        use std::collections::HashSet;
+
+        let mut board = Board::create();
+        let mut positions = HashSet::new();
+        positions.insert(board.clone());
+
+        board.play_and_remove_piece(&Move::new(Piece::from("DEXC"), Cell::from_index(&board, 0).unwrap())).unwrap();
+        assert!(positions.insert(board.clone()));
+        assert!(!positions.insert(Board::from_notation("DEXC.../..../..../.... - 2").unwrap()));
+
+        let moves: HashSet<Move> = board.get_available_moves().into_iter().collect();
+        assert_eq!(moves.len(), 15 * 15);
+    }
+
     #[test]
     fn test_display_board_not_empty() {
         if cfg!(feature = "display_console") {
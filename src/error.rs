@@ -1,5 +1,26 @@
 use std::fmt::Display;
-use crate::{board::Cell, piece::Piece};
+
+/// The families of errors, so the front ends can handle them without matching every variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// The move or the claim breaks the rules (cell already used, piece already played...)
+    IllegalMove,
+
+    /// The action is not the one the game is waiting for (placing before a piece has been given...)
+    OutOfTurn,
+
+    /// The game is over, nothing can be played anymore
+    GameOver,
+
+    /// A text (piece, position, rules or game record) can't be read
+    InvalidNotation,
+
+    /// The text can be read but describes a position or a game which can't happen
+    IllegalPosition,
+
+    /// The AI didn't manage to choose a move
+    Ai,
+}
 
 /// Represent the different errors which could happen during the game
 #[derive(Debug, PartialEq)]
@@ -7,27 +28,140 @@ pub enum ErrorGame {
     /// Try to play outside the board
     IndexOutOfBound,
 
-    /// The piece doesn't exist (should never happen)
-    PieceDoesNotExists,
+    /// There is no piece with this number (the number)
+    UnknownPiece(usize),
 
     /// The piece has already been played
     PieceDoesNotBelongPlayable,
 
-    /// A piece has already been played on this cell
-    CellIsNotEmpty(Cell, Piece),
+    /// A piece has already been played on this cell (cell index, text of the piece on it)
+    CellIsNotEmpty(usize, String),
+
+    /// The piece to take back is not on this cell (cell index, text of the piece)
+    PieceIsNotOnCell(usize, String),
+
+    /// The cells of the claim are not a line completed by the last move
+    InvalidQuartoClaim,
+
+    /// A piece has already been given, it has to be placed first
+    PieceAlreadySelected,
+
+    /// No piece has been given yet, the opponent has to choose one first
+    NoPieceSelected,
+
+    /// The player can't call "Quarto!" now
+    ClaimNotAllowed,
 
-    /// No best move has been found by the ai
-    NoBestMove
+    /// There is no move to undo
+    NothingToUndo,
+
+    /// There is no move to redo
+    NothingToRedo,
+
+    /// The game is over, no more turn can be played
+    GameIsOver,
+
+    /// The text can't be read as a piece (the text, the index of the first character which can't be read)
+    InvalidPieceNotation { input: String, position: usize },
+
+    /// The position notation can not be read (the reason)
+    InvalidNotation(String),
+
+    /// The rules are not known (the text of the rules)
+    UnknownRules(String),
+
+    /// The game record can not be read (the reason)
+    InvalidRecord(String),
+
+    /// The opening book can not be read (the reason)
+    InvalidBook(String),
+
+    /// The position has been read but can't happen in a game (the reason)
+    IllegalPosition(String),
+
+    /// A turn of the game record can not be played (the number of the turn, the reason)
+    IllegalTurn(usize, Box<ErrorGame>),
+
+    /// No best move has been found by the ai (the depth of the search, the notation of the position)
+    NoBestMove { depth: usize, position: String },
 }
 
 impl ErrorGame {
+    /// The family of the error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::IndexOutOfBound
+            | Self::UnknownPiece(_)
+            | Self::PieceDoesNotBelongPlayable
+            | Self::CellIsNotEmpty(..)
+            | Self::PieceIsNotOnCell(..)
+            | Self::InvalidQuartoClaim => ErrorKind::IllegalMove,
+            Self::PieceAlreadySelected
+            | Self::NoPieceSelected
+            | Self::ClaimNotAllowed
+            | Self::NothingToUndo
+            | Self::NothingToRedo => ErrorKind::OutOfTurn,
+            Self::GameIsOver => ErrorKind::GameOver,
+            Self::InvalidPieceNotation { .. }
+            | Self::InvalidNotation(_)
+            | Self::UnknownRules(_)
+            | Self::InvalidRecord(_)
+            | Self::InvalidBook(_) => ErrorKind::InvalidNotation,
+            Self::IllegalPosition(_) | Self::IllegalTurn(..) => ErrorKind::IllegalPosition,
+            Self::NoBestMove { .. } => ErrorKind::Ai,
+        }
+    }
+
+    /// A stable code for each error, which never changes between versions: the front ends can map it to their own messages.
+    /// The hundreds are the family: 1xx illegal move, 2xx out of turn, 3xx game over, 4xx invalid notation,
+    /// 5xx illegal position, 6xx AI
+    pub fn code(&self) -> u16 {
+        match self {
+            Self::IndexOutOfBound => 101,
+            Self::UnknownPiece(_) => 102,
+            Self::PieceDoesNotBelongPlayable => 103,
+            Self::CellIsNotEmpty(..) => 104,
+            Self::PieceIsNotOnCell(..) => 105,
+            Self::InvalidQuartoClaim => 106,
+            Self::PieceAlreadySelected => 201,
+            Self::NoPieceSelected => 202,
+            Self::ClaimNotAllowed => 203,
+            Self::NothingToUndo => 204,
+            Self::NothingToRedo => 205,
+            Self::GameIsOver => 301,
+            Self::InvalidPieceNotation { .. } => 401,
+            Self::InvalidNotation(_) => 402,
+            Self::UnknownRules(_) => 403,
+            Self::InvalidRecord(_) => 404,
+            Self::InvalidBook(_) => 405,
+            Self::IllegalPosition(_) => 501,
+            Self::IllegalTurn(..) => 502,
+            Self::NoBestMove { .. } => 601,
+        }
+    }
+
     pub fn message(&self) -> String {
         match self {
             Self::IndexOutOfBound => "The index is out of bound".to_owned(),
-            Self::PieceDoesNotExists => "This piece does not exists".to_owned(),
+            Self::UnknownPiece(number) => format!("There is no piece number {}", number),
             Self::PieceDoesNotBelongPlayable => "This piece has already been played".to_owned(),
-            Self::CellIsNotEmpty(cell, piece) => format!("The cell {} is not empty and have already the piece {}", cell, piece),
-            Self::NoBestMove => "No best move has been found by the ai".to_owned(),
+            Self::CellIsNotEmpty(cell, piece) => format!("The cell num {} is not empty and have already the piece {}", cell + 1, piece),
+            Self::PieceIsNotOnCell(cell, piece) => format!("The piece {} is not on the cell num {}", piece, cell + 1),
+            Self::InvalidQuartoClaim => "There is no Quarto on these cells".to_owned(),
+            Self::PieceAlreadySelected => "A piece has already been selected, it has to be placed first".to_owned(),
+            Self::NoPieceSelected => "No piece has been selected yet".to_owned(),
+            Self::ClaimNotAllowed => "You can't call Quarto now".to_owned(),
+            Self::NothingToUndo => "There is no move to undo".to_owned(),
+            Self::NothingToRedo => "There is no move to redo".to_owned(),
+            Self::GameIsOver => "The game is over".to_owned(),
+            Self::InvalidPieceNotation { input, position } => format!("The piece {} can't be read at the position {}", input, position + 1),
+            Self::InvalidNotation(reason) => format!("Invalid position notation: {}", reason),
+            Self::UnknownRules(rules) => format!("The rules {} are unknown", rules),
+            Self::InvalidRecord(reason) => format!("Invalid game record: {}", reason),
+            Self::InvalidBook(reason) => format!("Invalid opening book: {}", reason),
+            Self::IllegalPosition(reason) => format!("Illegal position: {}", reason),
+            Self::IllegalTurn(turn, _) => format!("The turn {} can not be played", turn),
+            Self::NoBestMove { depth, position } => format!("No best move has been found by the ai (depth {}) in the position {}", depth, position),
         }
     }
 }
@@ -36,4 +170,52 @@ impl Display for ErrorGame {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.message())
     }
-}
\ No newline at end of file
+}
+
+impl std::error::Error for ErrorGame {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::IllegalTurn(_, reason) => Some(reason.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+
+    #[test]
+    fn test_error_kind_and_code() {
+        assert_eq!(ErrorGame::CellIsNotEmpty(0, "DEXC".to_string()).kind(), ErrorKind::IllegalMove);
+        assert_eq!(ErrorGame::NoPieceSelected.kind(), ErrorKind::OutOfTurn);
+        assert_eq!(ErrorGame::GameIsOver.kind(), ErrorKind::GameOver);
+        assert_eq!(ErrorGame::UnknownRules("chess".to_string()).kind(), ErrorKind::InvalidNotation);
+        assert_eq!(ErrorGame::IllegalPosition(String::new()).kind(), ErrorKind::IllegalPosition);
+        assert_eq!(
+            ErrorGame::NoBestMove { depth: 2, position: String::new() }.kind(),
+            ErrorKind::Ai
+        );
+
+        assert_eq!(ErrorGame::IndexOutOfBound.code(), 101);
+        assert_eq!(ErrorGame::GameIsOver.code(), 301);
+        assert_eq!(ErrorGame::NoBestMove { depth: 2, position: String::new() }.code(), 601);
+    }
+
+    #[test]
+    fn test_error_source() {
+        let error = ErrorGame::IllegalTurn(2, Box::new(ErrorGame::PieceDoesNotBelongPlayable));
+        assert_eq!(error.to_string(), "The turn 2 can not be played");
+        assert_eq!(
+            error.source().map(|source| source.to_string()),
+            Some("This piece has already been played".to_string())
+        );
+        assert!(ErrorGame::GameIsOver.source().is_none());
+
+        // Can be used with the question mark in any function returning a boxed error
+        let boxed: Box<dyn Error> = Box::new(error);
+        assert!(boxed.source().is_some());
+    }
+}
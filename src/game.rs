@@ -1,11 +1,106 @@
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde")]
+use crate::{ai::budget::Budget, player::{PlayerType, AI}};
+#[cfg(all(feature = "serde", not(target_arch = "wasm32")))]
+use {crate::ai::budget::SystemClock, std::sync::Arc};
 use crate::{
-    board::{Board, Cell},
+    board::{Board, BoardIndex, BoardState, Cell},
     error::ErrorGame,
     piece::Piece,
     player::{Human, Player},
     r#move::Move,
+    rules::Rules,
 };
 
+/// A turn already played: the player who placed the piece and the move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Turn {
+    /// Index of the player who placed the piece
+    player: usize,
+
+    /// The piece handed over and the cell where it has been placed
+    played_move: Move,
+}
+
+impl Turn {
+    /// Index of the player who placed the piece
+    pub fn player(&self) -> usize {
+        self.player
+    }
+
+    /// The piece handed over and the cell where it has been placed
+    pub fn played_move(&self) -> Move {
+        self.played_move
+    }
+}
+
+/// The result of a finished game
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameOutcome {
+    /// Index of the player who won, None if it's a draw
+    winner: Option<usize>,
+
+    /// All the lines completed by the last move (with the misère rules, they made the other player win)
+    winning_lines: Vec<BTreeMap<usize, Cell>>,
+
+    /// The move which ended the game
+    final_move: Move,
+
+    /// Number of turns played
+    turns: usize,
+
+    /// The piece given but not placed yet when the game ended: the opponent claimed a missed Quarto after the gift
+    #[cfg_attr(feature = "serde", serde(default))]
+    given_piece: Option<Piece>,
+}
+
+impl GameOutcome {
+    pub fn winner(&self) -> Option<usize> {
+        self.winner
+    }
+
+    pub fn is_draw(&self) -> bool {
+        self.winner.is_none()
+    }
+
+    pub fn winning_lines(&self) -> &[BTreeMap<usize, Cell>] {
+        &self.winning_lines
+    }
+
+    pub fn final_move(&self) -> Move {
+        self.final_move
+    }
+
+    pub fn turns(&self) -> usize {
+        self.turns
+    }
+
+    pub fn given_piece(&self) -> Option<Piece> {
+        self.given_piece
+    }
+}
+
+/// The step of the turn the game is waiting for
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Phase {
+    /// The opponent has to choose the piece the current player will place
+    AwaitingPieceSelection,
+
+    /// The current player has to place the piece chosen by the opponent
+    AwaitingPlacement(Piece),
+
+    /// The last piece has been placed and completed a line, but "Quarto!" has not been called yet.
+    /// Only happens when the rules ask to call Quarto
+    AwaitingClaim,
+
+    /// The game is over
+    Finished(GameOutcome),
+}
+
 pub struct Game {
     /// The Quarto board
     board: Board,
@@ -15,26 +110,52 @@ pub struct Game {
 
     /// Current index player (I used index to avoid to borrow player and have to introduce lifetime)
     current_index_player: usize,
+
+    /// What the game is waiting for
+    phase: Phase,
+
+    /// The turns played, from the first one
+    history: Vec<Turn>,
+
+    /// The turns which have been undone, the last one is the next to redo
+    undone: Vec<Turn>,
 }
 
 impl Game {
     /// Start a new game
     pub fn start<P1: Player + 'static, P2: Player + 'static>(p1: P1, p2: P2) -> Game {
-        Game {
-            board: Board::create(),
-            players: [Box::new(p1), Box::new(p2)],
-            current_index_player: 0,
-        }
+        Game::start_dyn(Box::new(p1), Box::new(p2))
     }
 
     pub fn start_dyn(p1: Box<dyn Player>, p2: Box<dyn Player>) -> Game {
+        Game::start_dyn_with_rules(p1, p2, Rules::default())
+    }
+
+    /// Start a new game with specific rules
+    pub fn start_with_rules<P1: Player + 'static, P2: Player + 'static>(
+        p1: P1,
+        p2: P2,
+        rules: Rules,
+    ) -> Game {
+        Game::start_dyn_with_rules(Box::new(p1), Box::new(p2), rules)
+    }
+
+    pub fn start_dyn_with_rules(p1: Box<dyn Player>, p2: Box<dyn Player>, rules: Rules) -> Game {
         Game {
-            board: Board::create(),
+            board: Board::create_with_rules(rules),
             players: [p1, p2],
             current_index_player: 0,
+            phase: Phase::AwaitingPieceSelection,
+            history: vec![],
+            undone: vec![],
         }
     }
 
+    /// The rules of the game
+    pub fn rules(&self) -> Rules {
+        self.board.rules()
+    }
+
     /// Borrow the board
     pub fn get_board(&self) -> &Board {
         &self.board
@@ -46,28 +167,414 @@ impl Game {
     }
 
     /// Get a player by his index
-    pub fn get_player(&self, index: usize) -> &Box<dyn Player> {
-        &self.players[index]
+    pub fn get_player(&self, index: usize) -> &dyn Player {
+        self.players[index].as_ref()
     }
 
     /// Get the current player
-    pub fn current_player(&self) -> &Box<dyn Player> {
-        &self.players[self.current_index_player]
+    pub fn current_player(&self) -> &dyn Player {
+        self.players[self.current_index_player].as_ref()
     }
 
     /// Get the player which is not currently playing
-    pub fn opponent_player(&self) -> &Box<dyn Player> {
-        &self.players[(self.current_index_player as isize - 1).abs() as usize]
+    pub fn opponent_player(&self) -> &dyn Player {
+        self.players[(self.current_index_player as isize - 1).unsigned_abs()].as_ref()
     }
 
     /// Switch the current player to the other
-    pub fn switch_current_player(&mut self) {
-        self.current_index_player = (self.current_index_player as isize - 1).abs() as usize;
+    fn switch_current_player(&mut self) {
+        self.current_index_player = (self.current_index_player as isize - 1).unsigned_abs();
+    }
+
+    /// What the game is waiting for
+    pub fn phase(&self) -> &Phase {
+        &self.phase
     }
 
-    /// Play a turn with cell selected
+    /// Move to the next phase, the board keeps the piece to place in hand
+    fn set_phase(&mut self, phase: Phase) {
+        self.board.set_hand(match phase {
+            Phase::AwaitingPlacement(piece) => Some(piece),
+            _ => None,
+        });
+        self.phase = phase;
+    }
+
+    /// The opponent gives the piece the current player has to place
+    pub fn give_piece(&mut self, piece: Piece) -> Result<(), ErrorGame> {
+        match self.phase {
+            Phase::AwaitingPieceSelection => {
+                piece.to_index(&self.board)?;
+                self.set_phase(Phase::AwaitingPlacement(piece));
+                Ok(())
+            }
+            Phase::AwaitingPlacement(_) => Err(ErrorGame::PieceAlreadySelected),
+            Phase::AwaitingClaim | Phase::Finished(_) => Err(ErrorGame::GameIsOver),
+        }
+    }
+
+    /// The current player places the piece he has been given.
+    /// If the game is not over, the other player become the current player
+    pub fn place(&mut self, cell: Cell) -> Result<BoardState, ErrorGame> {
+        match self.phase {
+            Phase::AwaitingPlacement(piece) => {
+                let board_state = self.play_turn(Move::new(piece, cell))?;
+                // A new turn erase the turns which could have been redone
+                self.undone.clear();
+                Ok(board_state)
+            }
+            Phase::AwaitingPieceSelection => Err(ErrorGame::NoPieceSelected),
+            Phase::AwaitingClaim | Phase::Finished(_) => Err(ErrorGame::GameIsOver),
+        }
+    }
+
+    /// Play a full turn: give the piece then place it on the cell
+    /// (the piece can also be the one already given, after an undo for instance)
     pub fn play(&mut self, piece: Piece, cell: Cell) -> Result<Piece, ErrorGame> {
-        Ok(self.board.play_and_remove_piece(&Move::new(piece, cell))?)
+        let already_given = self.phase == Phase::AwaitingPlacement(piece);
+        if !already_given {
+            self.give_piece(piece)?;
+        }
+
+        if let Err(e) = self.place(cell) {
+            // The piece we gave is taken back, the turn is not played at all
+            if !already_given {
+                self.set_phase(Phase::AwaitingPieceSelection);
+            }
+            return Err(e);
+        }
+        Ok(piece)
+    }
+
+    /// Play the move on the board, record it and move to the next phase
+    fn play_turn(&mut self, played_move: Move) -> Result<BoardState, ErrorGame> {
+        self.board.play_and_remove_piece(&played_move)?;
+        self.history.push(Turn {
+            player: self.current_index_player,
+            played_move,
+        });
+
+        let board_state = self.board.board_state();
+        if self.rules().call_quarto() {
+            // The game only ends with a claim, or when there is nothing left to play
+            if self.board.can_play_another_turn() {
+                self.switch_current_player();
+                self.set_phase(Phase::AwaitingPieceSelection);
+            } else if !self.claimable_lines().is_empty() {
+                self.set_phase(Phase::AwaitingClaim);
+            } else {
+                self.finish_with_draw(played_move);
+            }
+        } else if board_state == BoardState::GameInProgress {
+            self.switch_current_player();
+            self.set_phase(Phase::AwaitingPieceSelection);
+        } else {
+            self.set_phase(Phase::Finished(GameOutcome {
+                winner: match board_state {
+                    BoardState::Win(_) => Some(self.current_index_player),
+                    BoardState::Loss(_) => Some((self.current_index_player as isize - 1).unsigned_abs()),
+                    _ => None,
+                },
+                winning_lines: self.board.winning_lines(),
+                final_move: played_move,
+                turns: self.history.len(),
+                given_piece: None,
+            }));
+        }
+
+        Ok(board_state)
+    }
+
+    /// End the game without winner
+    fn finish_with_draw(&mut self, final_move: Move) {
+        self.set_phase(Phase::Finished(GameOutcome {
+            winner: None,
+            winning_lines: vec![],
+            final_move,
+            turns: self.history.len(),
+            given_piece: None,
+        }));
+    }
+
+    /// The last move played, if any
+    pub fn last_move(&self) -> Option<Move> {
+        self.history.last().map(|turn| turn.played_move)
+    }
+
+    /// The player who can call "Quarto!" now: the one who just placed a piece until he gives the next one,
+    /// then his opponent until his own placement
+    fn claimer(&self) -> Option<usize> {
+        match self.phase {
+            Phase::AwaitingPieceSelection | Phase::AwaitingClaim => {
+                self.history.last().map(|turn| turn.player)
+            }
+            Phase::AwaitingPlacement(_) => self
+                .history
+                .last()
+                .filter(|turn| turn.player != self.current_index_player)
+                .map(|_| self.current_index_player),
+            Phase::Finished(_) => None,
+        }
+    }
+
+    /// The lines completed by the last move, which can still be claimed
+    fn claimable_lines(&self) -> Vec<u64> {
+        match self.last_move() {
+            Some(last_move) if self.rules().call_quarto() => {
+                self.board.completed_lines(last_move.cell().to_index())
+            }
+            _ => vec![],
+        }
+    }
+
+    /// The player calls "Quarto!" with the cells (by index) of a line completed by the last move.
+    /// Only available when the rules ask to call Quarto, the player wins the game if the claim is right
+    pub fn claim_quarto(&mut self, player: usize, line: &[usize]) -> Result<(), ErrorGame> {
+        if self.claimer() != Some(player) {
+            return Err(ErrorGame::ClaimNotAllowed);
+        }
+
+        // Each cell of the line has to be named once, a repeated cell doesn't stand for a missing one
+        let claimed = line
+            .iter()
+            .try_fold(0u64, |claimed, index| (*index < Board::NB_CELLS).then(|| claimed | (1 << index)))
+            .filter(|claimed| claimed.count_ones() as usize == line.len());
+        let claimed_line = self
+            .claimable_lines()
+            .into_iter()
+            .find(|mask| claimed == Some(*mask))
+            .ok_or(ErrorGame::InvalidQuartoClaim)?;
+
+        let final_move = self.last_move().ok_or(ErrorGame::ClaimNotAllowed)?;
+        // Like for a classic win, the winner stay the current player
+        self.current_index_player = player;
+        self.set_phase(Phase::Finished(GameOutcome {
+            winner: Some(player),
+            winning_lines: vec![self.board.mask_to_btree(claimed_line)],
+            final_move,
+            turns: self.history.len(),
+            given_piece: self.selected_piece(),
+        }));
+
+        Ok(())
+    }
+
+    /// The last piece has been placed and the player doesn't call "Quarto!": the game ends with a draw
+    pub fn decline_claim(&mut self) -> Result<(), ErrorGame> {
+        if self.phase != Phase::AwaitingClaim {
+            return Err(ErrorGame::ClaimNotAllowed);
+        }
+
+        let last_move = self.last_move().ok_or(ErrorGame::ClaimNotAllowed)?;
+        self.finish_with_draw(last_move);
+        Ok(())
+    }
+
+    /// Is the game finished ?
+    pub fn is_over(&self) -> bool {
+        matches!(self.phase, Phase::Finished(_))
+    }
+
+    /// The result of the game, None while the game is in progress
+    pub fn outcome(&self) -> Option<&GameOutcome> {
+        match &self.phase {
+            Phase::Finished(outcome) => Some(outcome),
+            _ => None,
+        }
+    }
+
+    /// The turns played since the beginning of the game
+    pub fn history(&self) -> &[Turn] {
+        &self.history
+    }
+
+    /// The piece handed over to the current player, which still has to be placed
+    pub fn selected_piece(&self) -> Option<Piece> {
+        match self.phase {
+            Phase::AwaitingPlacement(piece) => Some(piece),
+            _ => None,
+        }
+    }
+
+    /// Can we undo a turn ?
+    pub fn can_undo(&self) -> bool {
+        !self.history.is_empty()
+    }
+
+    /// Can we redo a turn ?
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// Take back the last turn.
+    /// The player who placed the piece become the current player again, with the same piece to place
+    pub fn undo(&mut self) -> Result<Move, ErrorGame> {
+        let turn = self.history.pop().ok_or(ErrorGame::NothingToUndo)?;
+
+        if let Err(e) = self.board.unplay(&turn.played_move) {
+            self.history.push(turn);
+            return Err(e);
+        }
+
+        self.current_index_player = turn.player;
+        self.set_phase(Phase::AwaitingPlacement(turn.played_move.piece()));
+        self.undone.push(turn);
+
+        Ok(turn.played_move)
+    }
+
+    /// Play again the last undone turn
+    pub fn redo(&mut self) -> Result<Move, ErrorGame> {
+        let turn = self.undone.pop().ok_or(ErrorGame::NothingToRedo)?;
+
+        // We can not redo if another piece has been given since the undo
+        let can_redo = match self.phase {
+            Phase::AwaitingPieceSelection => true,
+            Phase::AwaitingPlacement(piece) => piece == turn.played_move.piece(),
+            Phase::AwaitingClaim | Phase::Finished(_) => false,
+        };
+        if !can_redo {
+            self.undone.push(turn);
+            return Err(ErrorGame::NothingToRedo);
+        }
+
+        self.current_index_player = turn.player;
+        if let Err(e) = self.play_turn(turn.played_move) {
+            self.undone.push(turn);
+            return Err(e);
+        }
+
+        Ok(turn.played_move)
+    }
+}
+
+/// A player is serialized by his name, his type and the budget of an AI.
+/// The clock of the budget can't be saved: the AI is created back with the clock of the system
+/// (in wasm there is no such clock, the AI is created back with its default settings)
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedPlayer {
+    name: String,
+    player_type: PlayerType,
+    #[serde(default)]
+    budget: Option<Budget>,
+}
+
+#[cfg(feature = "serde")]
+impl SerializedPlayer {
+    fn into_player(self) -> Box<dyn Player> {
+        match (self.player_type, self.budget) {
+            (PlayerType::Human, _) => Box::new(Human::new(&self.name)),
+            (PlayerType::AI, None) => Box::new(AI::new()),
+            #[cfg(not(target_arch = "wasm32"))]
+            (PlayerType::AI, Some(budget)) => Box::new(AI::new().with_budget(budget, Arc::new(SystemClock::new()))),
+            #[cfg(target_arch = "wasm32")]
+            (PlayerType::AI, Some(_)) => Box::new(AI::new()),
+        }
+    }
+}
+
+/// The serialized game: the players, the board and where the game is.
+/// The turns are played again when the game is read, the board and the phase have to be the ones they lead to
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedGame {
+    players: [SerializedPlayer; 2],
+    board: Board,
+    current_player: usize,
+    phase: Phase,
+    history: Vec<Turn>,
+    undone: Vec<Turn>,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Game {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let player = |index: usize| SerializedPlayer {
+            name: self.players[index].name(),
+            player_type: self.players[index].player_type(),
+            budget: self.players[index].budget(),
+        };
+
+        SerializedGame {
+            players: [player(0), player(1)],
+            board: self.board.clone(),
+            current_player: self.current_index_player,
+            phase: self.phase.clone(),
+            history: self.history.clone(),
+            undone: self.undone.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Game {
+    /// Play again the turns of a serialized game, up to its phase
+    fn replay(serialized: SerializedGame) -> Result<Game, ErrorGame> {
+        let [p1, p2] = serialized.players.map(SerializedPlayer::into_player);
+        let mut game = Game::start_dyn_with_rules(p1, p2, serialized.board.rules());
+        for (number, turn) in serialized.history.iter().enumerate() {
+            let illegal_turn = |e| ErrorGame::IllegalTurn(number + 1, Box::new(e));
+            if turn.player != game.current_index_player {
+                return Err(illegal_turn(ErrorGame::IllegalPosition(format!("it's not the turn of the player {}", turn.player + 1))));
+            }
+            game.play(turn.played_move.piece(), turn.played_move.cell()).map_err(illegal_turn)?;
+        }
+
+        // The step after the last turn, which is not in the history: the piece given or the call of Quarto
+        match (&serialized.phase, &game.phase) {
+            (Phase::AwaitingPlacement(piece), Phase::AwaitingPieceSelection) => game.give_piece(*piece)?,
+            (Phase::Finished(outcome), Phase::AwaitingClaim) if outcome.winner.is_none() => game.decline_claim()?,
+            (Phase::Finished(outcome), Phase::AwaitingPieceSelection | Phase::AwaitingClaim)
+                if game.rules().call_quarto() =>
+            {
+                let winner = outcome.winner.ok_or(ErrorGame::ClaimNotAllowed)?;
+                // A missed win claimed by the opponent after the gift
+                if let Some(piece) = outcome.given_piece {
+                    game.give_piece(piece)?;
+                }
+                let line: Vec<usize> = outcome
+                    .winning_lines
+                    .first()
+                    .map(|line| line.keys().copied().collect())
+                    .unwrap_or_default();
+                game.claim_quarto(winner, &line)?;
+            }
+            _ => {}
+        }
+
+        if game.phase != serialized.phase
+            || game.board != serialized.board
+            || game.current_index_player != serialized.current_player
+        {
+            return Err(ErrorGame::IllegalPosition(
+                "the board or the phase is not the one of the turns played".to_string(),
+            ));
+        }
+
+        // The undone turns, from the last one, have to be playable again
+        let mut board = game.board.clone();
+        for turn in serialized.undone.iter().rev() {
+            board.play_and_remove_piece(&turn.played_move)?;
+        }
+        game.undone = serialized.undone;
+
+        Ok(game)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Game {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let serialized = SerializedGame::deserialize(deserializer)?;
+        if serialized.current_player > 1 {
+            return Err(D::Error::custom(ErrorGame::IndexOutOfBound));
+        }
+
+        Game::replay(serialized).map_err(D::Error::custom)
     }
 }
 
@@ -242,14 +749,260 @@ mod tests {
         assert_eq!(
             play_result,
             Err(ErrorGame::CellIsNotEmpty(
-                cell_error.unwrap(),
-                piece_error.unwrap()
+                cell_error.unwrap().to_index(),
+                piece_error.unwrap().as_text()
             ))
         );
 
         Ok(())
     }
 
+    #[test]
+    fn undo_and_redo_turns() -> Result<(), ErrorGame> {
+        let mut game = Game::from(("p1", "p2"));
+        assert_eq!(game.undo(), Err(ErrorGame::NothingToUndo));
+
+        let first_move = Move::new(Piece::from("DEXC"), Cell::from_index(&game.board, 0)?);
+        game.play(first_move.piece(), first_move.cell())?;
+
+        let second_move = Move::new(Piece::from("WFTS"), Cell::from_index(&game.board, 5)?);
+        game.play(second_move.piece(), second_move.cell())?;
+        assert_eq!(game.history().len(), 2);
+
+        // Take back the second turn: p2 has to place the same piece again
+        assert_eq!(game.undo()?, second_move);
+        assert_eq!(game.current_player().name(), "p2");
+        assert_eq!(game.selected_piece(), Some(Piece::from("WFTS")));
+        assert!(game.board[5].piece().is_none());
+        assert!(Piece::from("WFTS").to_index(&game.board).is_ok());
+
+        // Take back the first turn too
+        assert_eq!(game.undo()?, first_move);
+        assert_eq!(game.current_player().name(), "p1");
+        assert_eq!(game.selected_piece(), Some(Piece::from("DEXC")));
+        let mut empty_board = Board::create();
+        empty_board.set_hand(Some(Piece::from("DEXC")));
+        assert!(game.board == empty_board);
+
+        // Redo everything
+        assert_eq!(game.redo()?, first_move);
+        assert_eq!(game.redo()?, second_move);
+        assert_eq!(game.redo(), Err(ErrorGame::NothingToRedo));
+        assert_eq!(game.current_player().name(), "p1");
+        assert_eq!(game.selected_piece(), None);
+        assert_eq!(game.board[5].piece(), Some(Piece::from("WFTS")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn play_after_undo_should_clear_redo() -> Result<(), ErrorGame> {
+        let mut game = Game::from(("p1", "p2"));
+
+        game.play(Piece::from("DEXC"), Cell::from_index(&game.board, 0)?)?;
+        game.undo()?;
+        assert!(game.can_redo());
+
+        game.play(Piece::from("DEXC"), Cell::from_index(&game.board, 3)?)?;
+        assert!(!game.can_redo());
+        assert_eq!(game.redo(), Err(ErrorGame::NothingToRedo));
+        assert_eq!(game.history().len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn turn_phases_should_be_respected() -> Result<(), ErrorGame> {
+        let mut game = Game::from(("p1", "p2"));
+        assert_eq!(game.phase(), &Phase::AwaitingPieceSelection);
+        assert_eq!(
+            game.place(Cell::from_index(&game.board, 0)?),
+            Err(ErrorGame::NoPieceSelected)
+        );
+
+        game.give_piece(Piece::from("DEXC"))?;
+        assert_eq!(game.phase(), &Phase::AwaitingPlacement(Piece::from("DEXC")));
+        assert_eq!(
+            game.give_piece(Piece::from("WFTS")),
+            Err(ErrorGame::PieceAlreadySelected)
+        );
+
+        assert_eq!(
+            game.place(Cell::from_index(&game.board, 0)?)?,
+            BoardState::GameInProgress
+        );
+        assert_eq!(game.phase(), &Phase::AwaitingPieceSelection);
+        assert_eq!(game.current_player().name(), "p2");
+
+        // A piece already played can't be given
+        assert_eq!(
+            game.give_piece(Piece::from("DEXC")),
+            Err(ErrorGame::PieceDoesNotBelongPlayable)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn no_turn_after_the_game_is_over() -> Result<(), ErrorGame> {
+        let mut game = Game::from(("p1", "p2"));
+
+        for (piece, cell) in [("DEXC", 0), ("DFXC", 1), ("DETC", 2), ("DFTC", 3)] {
+            game.play(Piece::from(piece), Cell::from_index(&game.board, cell)?)?;
+        }
+
+        assert!(game.is_over());
+        let outcome = game.outcome().unwrap();
+        assert_eq!(outcome.winner(), Some(1));
+        assert_eq!(outcome.turns(), 4);
+        assert_eq!(outcome.final_move().piece(), Piece::from("DFTC"));
+        assert_eq!(outcome.winning_lines().len(), 1);
+        assert_eq!(
+            outcome.winning_lines()[0].keys().copied().collect::<Vec<usize>>(),
+            vec![0, 1, 2, 3]
+        );
+        // The winner stay the current player
+        assert_eq!(game.current_player().name(), "p2");
+        assert_eq!(
+            game.give_piece(Piece::from("WEXC")),
+            Err(ErrorGame::GameIsOver)
+        );
+
+        // But the last turn can be taken back
+        game.undo()?;
+        assert_eq!(game.phase(), &Phase::AwaitingPlacement(Piece::from("DFTC")));
+        assert!(game.outcome().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn square_wins_only_with_advanced_rules() -> Result<(), ErrorGame> {
+        let square = [("DEXC", 0), ("DFXC", 1), ("DETC", 4), ("DFTC", 5)];
+
+        let mut classic_game = Game::from(("p1", "p2"));
+        let mut advanced_game = Game::start_with_rules(
+            Human::new("p1"),
+            Human::new("p2"),
+            Rules::advanced(),
+        );
+        for (piece, cell) in square {
+            classic_game.play(Piece::from(piece), Cell::from_index(&classic_game.board, cell)?)?;
+            advanced_game.play(Piece::from(piece), Cell::from_index(&advanced_game.board, cell)?)?;
+        }
+
+        assert!(!classic_game.is_over());
+        assert_eq!(advanced_game.rules(), Rules::advanced());
+        assert_eq!(advanced_game.outcome().unwrap().winner(), Some(1));
+        assert_eq!(
+            advanced_game.outcome().unwrap().winning_lines()[0]
+                .keys()
+                .copied()
+                .collect::<Vec<usize>>(),
+            vec![0, 1, 4, 5]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn completing_a_line_loses_with_misere_rules() -> Result<(), ErrorGame> {
+        let mut game = Game::start_with_rules(
+            Human::new("p1"),
+            Human::new("p2"),
+            Rules::classic().with_misere(true),
+        );
+        for (piece, cell) in [("DEXC", 0), ("DFXC", 1), ("DETC", 2), ("DFTC", 3)] {
+            game.play(Piece::from(piece), Cell::from_index(&game.board, cell)?)?;
+        }
+
+        // p2 completed the line, so p1 wins
+        assert!(matches!(game.board.board_state(), BoardState::Loss(_)));
+        assert_eq!(game.outcome().unwrap().winner(), Some(0));
+
+        Ok(())
+    }
+
+    fn call_quarto_game() -> Game {
+        Game::start_with_rules(
+            Human::new("p1"),
+            Human::new("p2"),
+            Rules::classic().with_call_quarto(true),
+        )
+    }
+
+    #[test]
+    fn call_quarto_should_be_claimed_to_win() -> Result<(), ErrorGame> {
+        let mut game = call_quarto_game();
+        for (piece, cell) in [("DEXC", 0), ("DFXC", 1), ("DETC", 2), ("DFTC", 3)] {
+            game.play(Piece::from(piece), Cell::from_index(&game.board, cell)?)?;
+        }
+
+        // The line is complete but nobody called Quarto yet
+        assert!(!game.is_over());
+        assert_eq!(game.claim_quarto(0, &[0, 1, 2, 3]), Err(ErrorGame::ClaimNotAllowed));
+        assert_eq!(game.claim_quarto(1, &[0, 1, 2]), Err(ErrorGame::InvalidQuartoClaim));
+        assert_eq!(game.claim_quarto(1, &[0, 4, 8, 12]), Err(ErrorGame::InvalidQuartoClaim));
+        assert_eq!(game.claim_quarto(1, &[0, 0, 1, 2]), Err(ErrorGame::InvalidQuartoClaim));
+        assert_eq!(game.claim_quarto(1, &[0, 1, 2, 3, 3]), Err(ErrorGame::InvalidQuartoClaim));
+        assert_eq!(game.claim_quarto(1, &[0, 1, 2, 64]), Err(ErrorGame::InvalidQuartoClaim));
+
+        game.claim_quarto(1, &[3, 2, 1, 0])?;
+        let outcome = game.outcome().unwrap();
+        assert_eq!(outcome.winner(), Some(1));
+        assert_eq!(outcome.final_move().piece(), Piece::from("DFTC"));
+        assert_eq!(game.current_player().name(), "p2");
+
+        Ok(())
+    }
+
+    #[test]
+    fn missed_quarto_can_be_claimed_by_opponent() -> Result<(), ErrorGame> {
+        let mut game = call_quarto_game();
+        for (piece, cell) in [("DEXC", 0), ("DFXC", 1), ("DETC", 2), ("DFTC", 3)] {
+            game.play(Piece::from(piece), Cell::from_index(&game.board, cell)?)?;
+        }
+
+        // p2 missed his Quarto and gives a piece, p1 can claim it before placing the piece
+        game.give_piece(Piece::from("WEXC"))?;
+        assert_eq!(game.claim_quarto(1, &[0, 1, 2, 3]), Err(ErrorGame::ClaimNotAllowed));
+        game.claim_quarto(0, &[0, 1, 2, 3])?;
+        assert_eq!(game.outcome().unwrap().winner(), Some(0));
+        assert_eq!(game.outcome().unwrap().given_piece(), Some(Piece::from("WEXC")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn missed_quarto_expires_after_next_placement() -> Result<(), ErrorGame> {
+        let mut game = call_quarto_game();
+        for (piece, cell) in [("DEXC", 0), ("DFXC", 1), ("DETC", 2), ("DFTC", 3), ("WEXC", 15)] {
+            game.play(Piece::from(piece), Cell::from_index(&game.board, cell)?)?;
+        }
+
+        assert_eq!(game.claim_quarto(0, &[0, 1, 2, 3]), Err(ErrorGame::InvalidQuartoClaim));
+        assert_eq!(game.claim_quarto(1, &[0, 1, 2, 3]), Err(ErrorGame::ClaimNotAllowed));
+        assert_eq!(game.decline_claim(), Err(ErrorGame::ClaimNotAllowed));
+        assert!(!game.is_over());
+
+        Ok(())
+    }
+
+    #[test]
+    fn ai_should_claim_quarto() -> Result<(), ErrorGame> {
+        let mut game = call_quarto_game();
+        for (piece, cell) in [("DEXC", 0), ("DFXC", 1), ("DETC", 2), ("DFTC", 3)] {
+            game.play(Piece::from(piece), Cell::from_index(&game.board, cell)?)?;
+        }
+
+        let claim = AI::new().claim_quarto(game.get_board(), &game.last_move().unwrap());
+        assert_eq!(claim, Some(vec![0, 1, 2, 3]));
+        game.claim_quarto(1, &claim.unwrap())?;
+        assert!(game.is_over());
+
+        Ok(())
+    }
+
     /// Remove piece multiple time
     #[test]
     fn start_new_game_and_try_to_remove_multiple_piece_should_fail() -> Result<(), ErrorGame> {
@@ -265,4 +1018,116 @@ mod tests {
 
         Ok(())
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_game_should_keep_the_turns() -> Result<(), ErrorGame> {
+        let mut game = Game::start_with_rules(Human::new("p1"), AI::new(), Rules::advanced());
+        for (piece, cell) in [("DEXC", 0), ("DFXC", 1), ("WETC", 6)] {
+            game.play(Piece::from(piece), Cell::from_index(&game.board, cell)?)?;
+        }
+        game.undo()?;
+
+        let json = serde_json::to_string(&game).unwrap();
+        let mut restored: Game = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.get_player(0).name(), "p1");
+        assert!(restored.get_player(1).player_type() == PlayerType::AI);
+        assert!(restored.get_board() == game.get_board());
+        assert_eq!(restored.rules(), Rules::advanced());
+        assert_eq!(restored.current_player().name(), game.current_player().name());
+        assert_eq!(restored.phase(), &Phase::AwaitingPlacement(Piece::from("WETC")));
+        assert_eq!(restored.history(), game.history());
+        assert!(restored.can_redo());
+
+        // The game goes on from where it was saved
+        restored.place(Cell::from_index(&restored.board, 4)?)?;
+        restored.play(Piece::from("DFTC"), Cell::from_index(&restored.board, 5)?)?;
+        assert_eq!(restored.outcome().unwrap().winner(), Some(1));
+
+        let bytes = bincode::serialize(&restored).unwrap();
+        let finished: Game = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(finished.outcome(), restored.outcome());
+        assert_eq!(serde_json::to_string(&finished).unwrap(), serde_json::to_string(&restored).unwrap());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_game_should_keep_the_budget_of_the_ai() {
+        use crate::ai::budget::{Budget, SystemClock};
+        use std::sync::Arc;
+
+        let budget = Budget::nodes(1_000);
+        let game = Game::start(Human::new("p1"), AI::new().with_budget(budget, Arc::new(SystemClock::new())));
+
+        let restored: Game = serde_json::from_str(&serde_json::to_string(&game).unwrap()).unwrap();
+        assert_eq!(restored.get_player(0).budget(), None);
+        assert_eq!(restored.get_player(1).budget(), Some(budget));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_game_should_replay_the_turns() -> Result<(), ErrorGame> {
+        let mut game = Game::from(("p1", "p2"));
+        for (piece, cell) in [("DEXC", 0), ("DFXC", 1)] {
+            game.play(Piece::from(piece), Cell::from_index(&game.board, cell)?)?;
+        }
+        let json = serde_json::to_value(&game).unwrap();
+
+        // A turn which doesn't lead to the board
+        let mut moved = json.clone();
+        let other_cell = Move::new(Piece::from("DFXC"), Cell::from_index(&game.board, 2)?);
+        moved["history"][1]["played_move"] = serde_json::to_value(other_cell).unwrap();
+        assert!(serde_json::from_value::<Game>(moved).is_err());
+
+        // A turn played by the wrong player
+        let mut wrong_player = json.clone();
+        wrong_player["history"][1]["player"] = 0.into();
+        assert!(serde_json::from_value::<Game>(wrong_player).is_err());
+
+        // The same piece placed twice
+        let mut twice = json;
+        let same_piece = Move::new(Piece::from("DEXC"), Cell::from_index(&game.board, 1)?);
+        twice["history"][1]["played_move"] = serde_json::to_value(same_piece).unwrap();
+        assert!(serde_json::from_value::<Game>(twice).is_err());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_game_should_keep_a_missed_quarto_claimed_by_the_opponent() -> Result<(), ErrorGame> {
+        let mut game = call_quarto_game();
+        for (piece, cell) in [("DEXC", 0), ("DFXC", 1), ("DETC", 2), ("DFTC", 3)] {
+            game.play(Piece::from(piece), Cell::from_index(&game.board, cell)?)?;
+        }
+        game.give_piece(Piece::from("WEXC"))?;
+        game.claim_quarto(0, &[0, 1, 2, 3])?;
+
+        let restored: Game = serde_json::from_str(&serde_json::to_string(&game).unwrap()).unwrap();
+        assert_eq!(restored.outcome(), game.outcome());
+        assert!(restored.get_board() == game.get_board());
+
+        Ok(())
+    }
+
+    #[test]
+    fn board_notation_should_follow_the_game() -> Result<(), ErrorGame> {
+        let mut game = Game::from(("p1", "p2"));
+        assert_eq!(game.get_board().to_notation(), "..../..../..../.... - 1");
+
+        game.play(Piece::from("DEXC"), Cell::from_index(&game.board, 0)?)?;
+        game.give_piece(Piece::from("WFTS"))?;
+        assert_eq!(game.get_board().to_notation(), "DEXC.../..../..../.... WFTS 2");
+
+        game.place(Cell::from_index(&game.board, 6)?)?;
+        assert_eq!(game.get_board().to_notation(), "DEXC.../..WFTS./..../.... - 1");
+
+        game.undo()?;
+        assert_eq!(game.get_board().to_notation(), "DEXC.../..../..../.... WFTS 2");
+
+        Ok(())
+    }
 }
@@ -13,6 +13,10 @@ pub mod error;
 pub mod ai;
 pub mod r#move;
 pub mod player;
+pub mod rules;
+pub mod record;
+pub mod symmetry;
+mod zobrist;
 
 pub use game::Game;
 
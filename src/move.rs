@@ -2,53 +2,89 @@ use crate::board::BoardIndex;
 use core::fmt::Debug;
 use std::fmt::Display;
 
-use crate::board::Board;
-use crate::board::Cell;
+use crate::board::GenericBoard;
+use crate::board::GenericCell;
+use crate::board::HEIGHT_BOARD;
+use crate::board::NB_ATTRIBUTES;
+use crate::board::WIDTH_BOARD;
 use crate::error::ErrorGame;
-use crate::piece::Piece;
+use crate::piece::GenericPiece;
 
 /// Represent a move on the board
-#[derive(Clone, PartialEq, Copy, Eq)]
-pub struct Move {
-    piece: Piece,
-    cell: Cell,
+#[derive(Clone, PartialEq, Copy, Eq, Hash)]
+pub struct GenericMove<const W: usize, const H: usize, const A: usize> {
+    piece: GenericPiece<A>,
+    cell: GenericCell<W, H, A>,
 }
 
-impl Move {
+/// A move on the classic Quarto board
+pub type Move = GenericMove<WIDTH_BOARD, HEIGHT_BOARD, NB_ATTRIBUTES>;
+
+impl<const W: usize, const H: usize, const A: usize> GenericMove<W, H, A> {
     /// Create a new move
-    pub fn new(piece: Piece, cell: Cell) -> Move{
-        Move {
-            piece: piece,
-            cell: cell
+    pub fn new(piece: GenericPiece<A>, cell: GenericCell<W, H, A>) -> Self {
+        GenericMove {
+            piece,
+            cell
         }
     }
 
     /// Create a move from piece and cell index
-    pub fn from_index(index_piece: usize, index_cell: usize, board: &Board) -> Result<Move, ErrorGame> {
-        Ok(Move::new(Piece::from_index(&board, index_piece).unwrap(), Cell::from_index(&board, index_cell).unwrap()))
+    pub fn from_index(index_piece: usize, index_cell: usize, board: &GenericBoard<W, H, A>) -> Result<Self, ErrorGame> {
+        Ok(GenericMove::new(GenericPiece::from_index(board, index_piece)?, GenericCell::from_index(board, index_cell)?))
     }
 
     /// Convert move to tuple (easier for pattern matching)
-    pub fn to_tuple(&self, board: &Board) -> (usize, usize) {
-        (self.piece.to_index(&board).unwrap(), self.cell().to_index())
+    pub fn to_tuple(&self, board: &GenericBoard<W, H, A>) -> (usize, usize) {
+        (self.piece.to_index(board).unwrap(), self.cell().to_index())
     }
 
-    pub fn piece(&self) -> Piece {
+    pub fn piece(&self) -> GenericPiece<A> {
         self.piece
     }
 
-    pub fn cell(&self) -> Cell {
+    pub fn cell(&self) -> GenericCell<W, H, A> {
         self.cell
     }
 }
 
-impl Display for Move {
+/// The serialized move: the piece and the index of the cell
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedMove<const A: usize> {
+    piece: GenericPiece<A>,
+    cell: usize,
+}
+
+#[cfg(feature = "serde")]
+impl<const W: usize, const H: usize, const A: usize> serde::Serialize for GenericMove<W, H, A> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedMove {
+            piece: self.piece,
+            cell: self.cell.to_index(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const W: usize, const H: usize, const A: usize> serde::Deserialize<'de> for GenericMove<W, H, A> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let serialized = SerializedMove::<A>::deserialize(deserializer)?;
+        let cell = GenericCell::new(serialized.cell).map_err(D::Error::custom)?;
+        Ok(GenericMove::new(serialized.piece, cell))
+    }
+}
+
+impl<const W: usize, const H: usize, const A: usize> Display for GenericMove<W, H, A> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "(piece {} / cell num {})", (self.piece()), (self.cell().to_index() + 1))
     }
 }
 
-impl Debug for Move {
+impl<const W: usize, const H: usize, const A: usize> Debug for GenericMove<W, H, A> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "({}, i = {})", self.piece(), self.cell().to_index())
     }
@@ -69,16 +105,33 @@ mod tests {
         let range_move = 0..((WIDTH_BOARD * HEIGHT_BOARD) - 2);
 
         for index in range_move {
-            let piece = Piece::from_index(&board, index).expect(format!("Piece from index {} is not yet available", index).as_str());
-            let cell = Cell::from_index(&board, index).expect(format!("Cell from index {} is out of bounds", index).as_str());
+            let piece = Piece::from_index(&board, index).unwrap_or_else(|_| panic!("Piece from index {} is not yet available", index));
+            let cell = Cell::from_index(&board, index).unwrap_or_else(|_| panic!("Cell from index {} is out of bounds", index));
 
             trace!("Getting piece and cell from the same index = {}. Piece selected = {}", index, &piece);
 
-            board.play(piece, cell).expect(format!("Something is wrong when playing {} in cell {}", &piece, &cell).as_str());
-            board.remove(piece).expect(format!("Error when removing {} from the board", &piece).as_str());
+            board.play(piece, cell).unwrap_or_else(|_| panic!("Something is wrong when playing {} in cell {}", &piece, &cell));
+            board.remove(piece).unwrap_or_else(|_| panic!("Error when removing {} from the board", &piece));
         }
 
         // We have 2 pieces and cells which haven't been played, so we have 4 moves available
         assert_eq!(board.get_available_moves().len(), 4);
     }
-}
\ No newline at end of file
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_move() {
+        use crate::r#move::Move;
+
+        let board = Board::create();
+        let m = Move::new(Piece::from("WFTS"), Cell::from_index(&board, 9).unwrap());
+
+        let json = serde_json::to_string(&m).unwrap();
+        assert_eq!(json, r#"{"piece":"WFTS","cell":9}"#);
+        assert_eq!(serde_json::from_str::<Move>(&json).unwrap(), m);
+        assert!(serde_json::from_str::<Move>(r#"{"piece":"WFTS","cell":16}"#).is_err());
+
+        let bytes = bincode::serialize(&m).unwrap();
+        assert_eq!(bincode::deserialize::<Move>(&bytes).unwrap(), m);
+    }
+}
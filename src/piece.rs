@@ -1,9 +1,12 @@
-use crate::board::Board;
 use crate::board::BoardIndex;
+use crate::board::GenericBoard;
+use crate::board::NB_ATTRIBUTES;
 use crate::error::ErrorGame;
 use ansi_term::Colour as ConsoleColor;
 use enum_iterator::IntoEnumIterator;
+use std::fmt::Debug;
 use std::fmt::Display;
+use std::str::FromStr;
 
 /// Trait impleted by all type which caratcterise a piece
 pub trait PieceFeature {
@@ -22,6 +25,21 @@ pub trait PieceFeature {
         Self: Sized;
 }
 
+/// Read the value of an attribute from its acronym or its name (whatever the case):
+/// false for the first value of the pair, true for the second one
+fn read_attribute_value(s: &str, values: [(&str, &str); 2]) -> Result<bool, ErrorGame> {
+    [false, true]
+        .into_iter()
+        .find(|value| {
+            let (acronym, name) = values[*value as usize];
+            acronym.eq_ignore_ascii_case(s) || name.eq_ignore_ascii_case(s)
+        })
+        .ok_or(ErrorGame::InvalidPieceNotation {
+            input: s.to_string(),
+            position: 0,
+        })
+}
+
 /// The color type of a piece
 #[derive(Debug, Clone, Copy, Eq, PartialEq, IntoEnumIterator)]
 pub enum Color {
@@ -66,13 +84,19 @@ impl Display for Color {
     }
 }
 
+/// Read the color from its acronym or its name (whatever the case)
+impl FromStr for Color {
+    type Err = ErrorGame;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        read_attribute_value(s, ATTRIBUTES[0]).map(|value| if value { Self::Dark } else { Self::White })
+    }
+}
+
+/// Panic if the text is not a color, prefer `parse` for the user inputs
 impl From<&str> for Color {
     fn from(c: &str) -> Self {
-        match c {
-            "W" => Self::White,
-            "D" => Self::Dark,
-            e => panic!("Input {} cannot be convert to color", e),
-        }
+        c.parse().unwrap_or_else(|e: ErrorGame| panic!("{}", e))
     }
 }
 
@@ -119,13 +143,19 @@ impl Display for Hole {
     }
 }
 
+/// Read the hole from its acronym or its name (whatever the case)
+impl FromStr for Hole {
+    type Err = ErrorGame;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        read_attribute_value(s, ATTRIBUTES[1]).map(|value| if value { Self::Full } else { Self::Empty })
+    }
+}
+
+/// Panic if the text is not a hole, prefer `parse` for the user inputs
 impl From<&str> for Hole {
     fn from(c: &str) -> Self {
-        match c {
-            "E" => Self::Empty,
-            "F" => Self::Full,
-            e => panic!("Input {} cannot be convert to color", e),
-        }
+        c.parse().unwrap_or_else(|e: ErrorGame| panic!("{}", e))
     }
 }
 
@@ -173,13 +203,19 @@ impl Display for Height {
     }
 }
 
+/// Read the height from its acronym or its name (whatever the case)
+impl FromStr for Height {
+    type Err = ErrorGame;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        read_attribute_value(s, ATTRIBUTES[2]).map(|value| if value { Self::Tall } else { Self::Small })
+    }
+}
+
+/// Panic if the text is not a height, prefer `parse` for the user inputs
 impl From<&str> for Height {
     fn from(c: &str) -> Self {
-        match c {
-            "X" => Self::Small,
-            "T" => Self::Tall,
-            e => panic!("Input {} cannot be convert to color", e),
-        }
+        c.parse().unwrap_or_else(|e: ErrorGame| panic!("{}", e))
     }
 }
 
@@ -227,112 +263,335 @@ impl Display for Shape {
     }
 }
 
+/// Read the shape from its acronym or its name (whatever the case)
+impl FromStr for Shape {
+    type Err = ErrorGame;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        read_attribute_value(s, ATTRIBUTES[3]).map(|value| if value { Self::Square } else { Self::Circle })
+    }
+}
+
+/// Panic if the text is not a shape, prefer `parse` for the user inputs
 impl From<&str> for Shape {
     fn from(c: &str) -> Self {
-        match c {
-            "C" => Self::Circle,
-            "S" => Self::Square,
-            e => panic!("Input {} cannot be convert to color", e),
-        }
+        c.parse().unwrap_or_else(|e: ErrorGame| panic!("{}", e))
     }
 }
 
-//Represent piece settings
-#[derive(Debug, Clone, Copy, Eq)]
-pub struct Piece {
-    pub color: Color,
-    pub hole: Hole,
-    pub height: Height,
-    pub shape: Shape,
+/// The maximum number of attributes of a piece, so the pieces can be stored in 64 bits masks
+pub const MAX_ATTRIBUTES: usize = 6;
+
+/// The acronym and the name of each value of the attributes (first value, second value).
+/// The four first ones are the classic Quarto attributes, the others are used by the bigger variants
+const ATTRIBUTES: [[(&str, &str); 2]; MAX_ATTRIBUTES] = [
+    [("W", "White"), ("D", "Dark")],
+    [("E", "Empty"), ("F", "Full")],
+    [("X", "Small"), ("T", "Tall")],
+    [("C", "Circle"), ("S", "Square")],
+    [("P", "Plain"), ("M", "Marked")],
+    [("L", "Light"), ("H", "Heavy")],
+];
+
+/// Represent piece settings as its number: from the most significant bit, one bit per attribute
+/// which is set when the piece has the second value of the attribute.
+/// There is `A` attributes, so 2^A different pieces
+#[derive(Clone, Copy, Eq, PartialEq, Hash)]
+pub struct GenericPiece<const A: usize> {
+    number: u8,
+}
+
+/// The classic Quarto piece with 4 attributes: color, hole, height and shape
+pub type Piece = GenericPiece<NB_ATTRIBUTES>;
+
+impl<const A: usize> GenericPiece<A> {
+    /// The number of different pieces
+    pub const NB_PIECES: usize = 1 << A;
+
+    /// Return the number of the piece (0 to 2^A - 1).
+    /// For the classic pieces, from the most significant bit: color (dark), hole (full), height (tall), shape (square)
+    pub fn number(&self) -> u8 {
+        self.number
+    }
+
+    /// Create the piece from its number
+    pub fn from_number(number: u8) -> Result<Self, ErrorGame> {
+        if number as usize >= Self::NB_PIECES {
+            return Err(ErrorGame::UnknownPiece(number as usize));
+        }
+        Ok(GenericPiece { number })
+    }
+
+    /// Return if the piece has the second value of the attribute (0 = dark, 1 = full, 2 = tall, 3 = square)
+    pub fn has_attribute(&self, attribute: usize) -> bool {
+        attribute < A && self.number & (1 << (A - 1 - attribute)) != 0
+    }
+
+    /// The mask of the piece numbers which have the second value of the attribute
+    pub fn numbers_with_attribute(attribute: usize) -> u64 {
+        (0..Self::NB_PIECES)
+            .filter(|number| GenericPiece::<A> { number: *number as u8 }.has_attribute(attribute))
+            .fold(0, |mask, number| mask | (1 << number))
+    }
+
+    /// The acronym of the attribute value, used to write the piece as text
+    pub fn attribute_acronym(attribute: usize, value: bool) -> &'static str {
+        ATTRIBUTES[attribute][value as usize].0
+    }
+
+    /// The full name of the attribute value
+    pub fn attribute_name(attribute: usize, value: bool) -> &'static str {
+        ATTRIBUTES[attribute][value as usize].1
+    }
+
+    /// The classic attributes, to reuse their colors
+    fn feature(attribute: usize) -> Option<Vec<Box<dyn PieceFeature>>> {
+        match attribute {
+            0 => Some(Color::to_vec_boxed()),
+            1 => Some(Hole::to_vec_boxed()),
+            2 => Some(Height::to_vec_boxed()),
+            3 => Some(Shape::to_vec_boxed()),
+            _ => None,
+        }
+    }
+
+    /// The acronym of the attribute value painted like in the legend of the board
+    pub fn colored_acronym(attribute: usize, value: bool) -> String {
+        match Self::feature(attribute) {
+            Some(features) => {
+                let feature = &features[value as usize];
+                feature.color()[value as usize].paint(feature.acronym()).to_string()
+            }
+            None => Self::attribute_acronym(attribute, value).to_string(),
+        }
+    }
+
+    pub fn as_text(&self) -> String {
+        (0..A)
+            .map(|attribute| Self::attribute_acronym(attribute, self.has_attribute(attribute)))
+            .collect()
+    }
+
+    /// Create the piece from its text, one acronym per attribute (the reverse of `as_text`)
+    pub fn from_text(text: &str) -> Result<Self, ErrorGame> {
+        let invalid = |position: usize| ErrorGame::InvalidPieceNotation {
+            input: text.to_string(),
+            position,
+        };
+
+        let acronyms: Vec<char> = text.chars().collect();
+        let number = (0..A).try_fold(0, |number, attribute| {
+            let acronym = acronyms.get(attribute).ok_or_else(|| invalid(attribute))?;
+            [false, true]
+                .into_iter()
+                .find(|value| Self::attribute_acronym(attribute, *value).eq_ignore_ascii_case(&acronym.to_string()))
+                .map(|value| (number << 1) | value as u8)
+                .ok_or_else(|| invalid(attribute))
+        })?;
+
+        if acronyms.len() > A {
+            return Err(invalid(A));
+        }
+        Self::from_number(number)
+    }
+
+    /// Create the piece from the names of its attributes, like "Dark Full Tall Square" (whatever the case)
+    pub fn from_names(names: &str) -> Result<Self, ErrorGame> {
+        let invalid = |position: usize| ErrorGame::InvalidPieceNotation {
+            input: names.to_string(),
+            position,
+        };
+
+        // The position (in characters, like `from_text`) of each word in the input,
+        // a whitespace is added at the end to close the last word
+        let nb_chars = names.chars().count();
+        let mut words: Vec<(usize, &str)> = vec![];
+        let mut start = None;
+        for (position, (index, c)) in names.char_indices().chain([(names.len(), ' ')]).enumerate() {
+            match (start, c.is_whitespace()) {
+                (None, false) => start = Some((position, index)),
+                (Some((begin_position, begin)), true) => {
+                    words.push((begin_position, &names[begin..index]));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+
+        let number = (0..A).try_fold(0, |number, attribute| {
+            let (position, name) = words.get(attribute).copied().unwrap_or((nb_chars, ""));
+            [false, true]
+                .into_iter()
+                .find(|value| Self::attribute_name(attribute, *value).eq_ignore_ascii_case(name))
+                .map(|value| (number << 1) | value as u8)
+                .ok_or_else(|| invalid(position))
+        })?;
+
+        if let Some((position, _)) = words.get(A) {
+            return Err(invalid(*position));
+        }
+        Self::from_number(number)
+    }
 }
 
 impl Piece {
     /// Create a new piece
     pub fn new(color: Color, hole: Hole, height: Height, shape: Shape) -> Self {
-        Self {
-            color,
-            hole,
-            height,
-            shape,
-        }
+        let number = [
+            color == Color::Dark,
+            hole == Hole::Full,
+            height == Height::Tall,
+            shape == Shape::Square,
+        ]
+        .into_iter()
+        .fold(0, |number, attribute| (number << 1) | attribute as u8);
+
+        Self { number }
     }
+
+    pub fn color(&self) -> Color {
+        if self.has_attribute(0) { Color::Dark } else { Color::White }
+    }
+
+    pub fn hole(&self) -> Hole {
+        if self.has_attribute(1) { Hole::Full } else { Hole::Empty }
+    }
+
+    pub fn height(&self) -> Height {
+        if self.has_attribute(2) { Height::Tall } else { Height::Small }
+    }
+
+    pub fn shape(&self) -> Shape {
+        if self.has_attribute(3) { Shape::Square } else { Shape::Circle }
+    }
+
     /// Check if the piece vector is a winning combinaison
-    pub fn check_piece_is_winning(pieces: &mut Vec<Piece>) -> bool {
+    pub fn check_piece_is_winning(pieces: &mut [Piece]) -> bool {
         //We need at least a 4 size vector
         if pieces.len() < 4 {
             return false;
         }
 
         let winning_condition = vec![
-            pieces.into_iter().all(|p| p.color == Color::Dark),
-            pieces.into_iter().all(|p| p.color == Color::White),
-            pieces.into_iter().all(|p| p.height == Height::Small),
-            pieces.into_iter().all(|p| p.height == Height::Tall),
-            pieces.into_iter().all(|p| p.hole == Hole::Empty),
-            pieces.into_iter().all(|p| p.hole == Hole::Full),
-            pieces.into_iter().all(|p| p.shape == Shape::Circle),
-            pieces.into_iter().all(|p| p.shape == Shape::Square),
+            pieces.iter().all(|p| p.color() == Color::Dark),
+            pieces.iter().all(|p| p.color() == Color::White),
+            pieces.iter().all(|p| p.height() == Height::Small),
+            pieces.iter().all(|p| p.height() == Height::Tall),
+            pieces.iter().all(|p| p.hole() == Hole::Empty),
+            pieces.iter().all(|p| p.hole() == Hole::Full),
+            pieces.iter().all(|p| p.shape() == Shape::Circle),
+            pieces.iter().all(|p| p.shape() == Shape::Square),
         ];
 
         trace!("Piece >> check_piece_is_winning : {:?}", winning_condition);
 
         winning_condition.iter().any(|w| *w)
     }
+}
 
-    pub fn as_text(&self) -> String {
-        format!("{}{}{}{}", self.color.acronym(), self.hole.acronym(), self.height.acronym(), self.shape.acronym())
+impl<const W: usize, const H: usize, const A: usize> BoardIndex<W, H, A> for GenericPiece<A> {
+    fn from_index(board: &GenericBoard<W, H, A>, index: usize) -> Result<Self, ErrorGame> {
+        if !board.is_available(index) {
+            return Err(ErrorGame::PieceDoesNotBelongPlayable);
+        }
+        GenericPiece::from_number(index as u8)
+    }
+
+    fn to_index(&self, board: &GenericBoard<W, H, A>) -> Result<usize, ErrorGame> {
+        let index = self.number() as usize;
+        if !board.is_available(index) {
+            return Err(ErrorGame::PieceDoesNotBelongPlayable);
+        }
+        Ok(index)
     }
 }
 
-impl BoardIndex for Piece {
-    fn from_index(board: &Board, index: usize) -> Result<Piece, ErrorGame> {
-        Ok(board
-            .get_available_pieces()
-            .get(&index)
-            .ok_or(ErrorGame::PieceDoesNotBelongPlayable)?
-            .to_owned())
+impl<const A: usize> Debug for GenericPiece<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_text())
     }
+}
 
-    fn to_index(&self, board: &Board) -> Result<usize, ErrorGame> {
-        board
-            .get_available_pieces()
-            .into_iter()
-            .find_map(|(i, p)| if &p == self { Some(i) } else { None })
-            .ok_or(ErrorGame::PieceDoesNotBelongPlayable)
+impl<const A: usize> Display for GenericPiece<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for attribute in 0..A {
+            let value = self.has_attribute(attribute);
+            match attribute {
+                0 => write!(f, "{}", if value { Color::Dark } else { Color::White })?,
+                1 => write!(f, "{}", if value { Hole::Full } else { Hole::Empty })?,
+                2 => write!(f, "{}", if value { Height::Tall } else { Height::Small })?,
+                3 => write!(f, "{}", if value { Shape::Square } else { Shape::Circle })?,
+                _ => write!(f, "{}", Self::attribute_acronym(attribute, value))?,
+            }
+        }
+        Ok(())
     }
 }
 
-impl PartialEq for Piece {
-    fn eq(&self, other: &Self) -> bool {
-        self.color == other.color
-            && self.hole == other.hole
-            && self.height == other.height
-            && self.shape == other.shape
+/// A piece is serialized as its text (like "DFTS") for the human readable formats, else as its number
+#[cfg(feature = "serde")]
+impl<const A: usize> serde::Serialize for GenericPiece<A> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.as_text())
+        } else {
+            serializer.serialize_u8(self.number)
+        }
     }
 }
 
-impl Display for Piece {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}{}{}{}",
-            self.color, self.hole, self.height, self.shape
-        )
+#[cfg(feature = "serde")]
+impl<'de, const A: usize> serde::Deserialize<'de> for GenericPiece<A> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let piece = if deserializer.is_human_readable() {
+            GenericPiece::from_text(&String::deserialize(deserializer)?)
+        } else {
+            GenericPiece::from_number(u8::deserialize(deserializer)?)
+        };
+        piece.map_err(D::Error::custom)
+    }
+}
+
+/// Read a piece from its text ("DFTS"), the names of its attributes ("Dark Full Tall Square")
+/// or its number (0 to 2^A - 1). The position of the error is the index of the character which can't be read
+impl<const A: usize> FromStr for GenericPiece<A> {
+    type Err = ErrorGame;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text = s.trim();
+        if !text.is_empty() && text.chars().all(|c| c.is_ascii_digit()) {
+            return text
+                .parse::<u8>()
+                .ok()
+                .and_then(|number| Self::from_number(number).ok())
+                .ok_or(ErrorGame::InvalidPieceNotation {
+                    input: s.to_string(),
+                    position: 0,
+                });
+        }
+
+        let offset = s.chars().count() - s.trim_start().chars().count();
+        let result = if text.contains(char::is_whitespace) {
+            Self::from_names(text)
+        } else {
+            Self::from_text(text)
+        };
+        result.map_err(|e| match e {
+            ErrorGame::InvalidPieceNotation { position, .. } => ErrorGame::InvalidPieceNotation {
+                input: s.to_string(),
+                position: position + offset,
+            },
+            e => e,
+        })
     }
 }
 
+/// Panic if the text is not a piece, prefer `parse` for the user inputs
 impl From<&str> for Piece {
     fn from(s: &str) -> Self {
-        //4 character max
-        assert!(s.chars().count() == 4);
-
-        let lower_s = s.to_uppercase();
-        Piece::new(
-            Color::from(&lower_s[0..=0]),
-            Hole::from(&lower_s[1..=1]),
-            Height::from(&lower_s[2..=2]),
-            Shape::from(&lower_s[3..=3]),
-        )
+        s.parse().unwrap_or_else(|e: ErrorGame| panic!("{}", e))
     }
 }
 
@@ -343,11 +602,12 @@ impl From<[char; 4]> for Piece {
 }
 #[cfg(test)]
 mod tests {
-    use crate::{board::Cell};
+    use crate::board::{Board, Cell};
 
     use super::*;
 
     #[test]
+    #[allow(clippy::clone_on_copy)]
     fn test_equality_basic() {
         let piece_base = Piece::new(Color::Dark, Hole::Empty, Height::Small, Shape::Circle);
         let piece_base_equal = Piece::new(Color::Dark, Hole::Empty, Height::Small, Shape::Circle);
@@ -423,6 +683,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_are_cells_winning() {
         let mut v_3 = vec![
             Piece::new(Color::White, Hole::Empty, Height::Small, Shape::Circle),
@@ -455,6 +716,18 @@ mod tests {
         println!("{}", x);
     }
 
+    #[test]
+    fn test_piece_number() {
+        assert_eq!(Piece::from("WEXC").number(), 0);
+        assert_eq!(Piece::from("DEXC").number(), 8);
+        assert_eq!(Piece::from("DFTS").number(), 15);
+
+        for number in 0..16 {
+            assert_eq!(Piece::from_number(number).unwrap().number(), number);
+        }
+        assert_eq!(Piece::from_number(16), Err(ErrorGame::UnknownPiece(16)));
+    }
+
     #[test]
     fn test_piece_position() {
         let mut board = Board::create();
@@ -483,4 +756,83 @@ mod tests {
         // The index of the last piece is always 15
         assert_eq!(last_piece.to_index(&board).unwrap(), 15);
     }
+
+    #[test]
+    fn test_piece_from_text() {
+        assert_eq!(Piece::from_text("DFTS"), Ok(Piece::from_number(15).unwrap()));
+        assert_eq!(Piece::from_text("wexc"), Ok(Piece::from_number(0).unwrap()));
+        assert_eq!(GenericPiece::<5>::from_text("DEXCM").unwrap().number(), 0b10001);
+        assert_eq!(
+            Piece::from_text("DFT"),
+            Err(ErrorGame::InvalidPieceNotation { input: "DFT".to_string(), position: 3 })
+        );
+        assert_eq!(
+            Piece::from_text("DFTZ"),
+            Err(ErrorGame::InvalidPieceNotation { input: "DFTZ".to_string(), position: 3 })
+        );
+    }
+
+    #[test]
+    fn test_piece_from_str() {
+        let piece = Piece::new(Color::Dark, Hole::Full, Height::Tall, Shape::Square);
+        assert_eq!("DFTS".parse::<Piece>(), Ok(piece));
+        assert_eq!(" dfts ".parse::<Piece>(), Ok(piece));
+        assert_eq!("Dark Full Tall Square".parse::<Piece>(), Ok(piece));
+        assert_eq!("dark  full tall   SQUARE".parse::<Piece>(), Ok(piece));
+        assert_eq!("15".parse::<Piece>(), Ok(piece));
+        assert_eq!("0".parse::<Piece>(), Ok(Piece::from("WEXC")));
+        assert_eq!("31".parse::<GenericPiece<5>>().unwrap().as_text(), "DFTSM");
+
+        let error = |input: &str, position: usize| {
+            Err(ErrorGame::InvalidPieceNotation { input: input.to_string(), position })
+        };
+        assert_eq!("DESCC".parse::<Piece>(), error("DESCC", 2));
+        assert_eq!("DEXCC".parse::<Piece>(), error("DEXCC", 4));
+        assert_eq!("".parse::<Piece>(), error("", 0));
+        assert_eq!("16".parse::<Piece>(), error("16", 0));
+        assert_eq!("Dark Full Big Square".parse::<Piece>(), error("Dark Full Big Square", 10));
+        assert_eq!("Dark  Full\tBig Square".parse::<Piece>(), error("Dark  Full\tBig Square", 11));
+        assert_eq!("Dark Full Tall".parse::<Piece>(), error("Dark Full Tall", 14));
+        assert_eq!("Dark Full Tall Square Heavy".parse::<Piece>(), error("Dark Full Tall Square Heavy", 22));
+        assert_eq!("  DFTZ".parse::<Piece>(), error("  DFTZ", 5));
+
+        // The positions are counted in characters, not in bytes
+        assert_eq!("Dark\u{3000}Full Big Square".parse::<Piece>(), error("Dark\u{3000}Full Big Square", 10));
+        assert_eq!("Dark\u{3000}Full Tall Square Heavy".parse::<Piece>(), error("Dark\u{3000}Full Tall Square Heavy", 22));
+        assert_eq!("Dark\u{3000}Full Tall".parse::<Piece>(), error("Dark\u{3000}Full Tall", 14));
+        assert_eq!("Dark Full Tall Squâre".parse::<Piece>(), error("Dark Full Tall Squâre", 15));
+        assert_eq!("\u{3000}DFTZ".parse::<Piece>(), error("\u{3000}DFTZ", 4));
+    }
+
+    #[test]
+    fn test_attributes_from_str() {
+        assert_eq!("D".parse::<Color>(), Ok(Color::Dark));
+        assert_eq!("white".parse::<Color>(), Ok(Color::White));
+        assert_eq!("f".parse::<Hole>(), Ok(Hole::Full));
+        assert_eq!("Empty".parse::<Hole>(), Ok(Hole::Empty));
+        assert_eq!("X".parse::<Height>(), Ok(Height::Small));
+        assert_eq!("TALL".parse::<Height>(), Ok(Height::Tall));
+        assert_eq!("s".parse::<Shape>(), Ok(Shape::Square));
+        assert_eq!("Circle".parse::<Shape>(), Ok(Shape::Circle));
+        assert_eq!(
+            "Z".parse::<Shape>(),
+            Err(ErrorGame::InvalidPieceNotation { input: "Z".to_string(), position: 0 })
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_piece() {
+        let piece = Piece::from("DEXS");
+
+        let json = serde_json::to_string(&piece).unwrap();
+        assert_eq!(json, "\"DEXS\"");
+        assert_eq!(serde_json::from_str::<Piece>(&json).unwrap(), piece);
+        assert!(serde_json::from_str::<Piece>("\"DEX\"").is_err());
+
+        let bytes = bincode::serialize(&piece).unwrap();
+        assert_eq!(bytes, vec![0b1001]);
+        assert_eq!(bincode::deserialize::<Piece>(&bytes).unwrap(), piece);
+        assert!(bincode::deserialize::<Piece>(&[16]).is_err());
+    }
 }
@@ -1,8 +1,15 @@
-use std::fmt::Display;
+use std::{cell::RefCell, fmt::Display, sync::Arc};
 
-use crate::{piece::Piece, board::Board, r#move::Move, error::ErrorGame, ai::adequat_strategy};
+use crate::{
+    piece::Piece,
+    board::Board,
+    r#move::Move,
+    error::ErrorGame,
+    ai::{adequat_strategy, adequat_strategy_kind, completed_line, adequat_strategy_with_budget, budget::{Budget, Clock}, Strategy, StrategyKind},
+};
 
 #[derive(PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PlayerType {
     /// A human player
     Human,
@@ -22,6 +29,14 @@ pub trait Player {
 
     /// Choose a piece for the opponent
     fn choose_piece_for_opponent(&self, board: &Board) -> Piece;
+
+    /// Call "Quarto!" with the cells of a line completed by the last move (when the rules ask for it)
+    fn claim_quarto(&self, board: &Board, last_move: &Move) -> Option<Vec<usize>>;
+
+    /// How long the player thinks, only an AI can have a budget
+    fn budget(&self) -> Option<Budget> {
+        None
+    }
 }
 
 /// Represent a player (humain or AI)
@@ -53,17 +68,46 @@ impl Player for Human {
     fn choose_piece_for_opponent(&self, _board: &Board) -> Piece {
         unimplemented!()
     }
+
+    /// The claim of a human comes from the input, not from the player
+    fn claim_quarto(&self, _board: &Board, _last_move: &Move) -> Option<Vec<usize>> {
+        None
+    }
 }
 
-impl Display for dyn Player {
+impl Display for dyn Player + '_ {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.name())
     }
 }
 
+/// The strategy of the last search, with its kind.
+/// A clone of the AI starts without strategy, it doesn't share what this one has learnt
+#[derive(Default)]
+struct KeptStrategy(RefCell<Option<(StrategyKind, Box<dyn Strategy>)>>);
+
+impl Clone for KeptStrategy {
+    fn clone(&self) -> Self {
+        KeptStrategy::default()
+    }
+}
+
+/// The strategy itself is not displayed, only its kind
+impl std::fmt::Debug for KeptStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = self.0.borrow().as_ref().map(|(kind, _)| *kind);
+        f.debug_tuple("KeptStrategy").field(&kind).finish()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AI {
     name: String,
+    /// How long the AI thinks, the depth of the search comes from the number of pieces played otherwise
+    budget: Option<(Budget, Arc<dyn Clock>)>,
+    /// Kept from a call to the next one, so the transposition table found while choosing the move
+    /// is used to choose the piece (and the next move)
+    strategy: KeptStrategy,
 }
 
 impl AI {
@@ -71,14 +115,46 @@ impl AI {
     pub fn new() -> AI {
         AI {
             name: AI::default_name(),
+            budget: None,
+            strategy: KeptStrategy::default(),
         }
     }
 
+    /// Think with this budget, the time is given by the clock
+    pub fn with_budget(mut self, budget: Budget, clock: Arc<dyn Clock>) -> AI {
+        self.budget = Some((budget, clock));
+        self.strategy = KeptStrategy::default();
+        self
+    }
+
+    /// Search with the strategy adequat for the board. The strategy of the last call is used again
+    /// if it's still the one to choose, it's only created again when the kind of strategy changes
+    fn with_strategy<T>(&self, board: &Board, search: impl FnOnce(&mut dyn Strategy) -> T) -> T {
+        let kind = adequat_strategy_kind(board, self.budget.is_some());
+        let mut kept = self.strategy.0.borrow_mut();
+        if kept.as_ref().map(|(kept_kind, _)| *kept_kind) != Some(kind) {
+            let strategy = match &self.budget {
+                Some((budget, clock)) => adequat_strategy_with_budget(board, *budget, Arc::clone(clock)),
+                None => adequat_strategy(board),
+            };
+            *kept = Some((kind, strategy));
+        }
+
+        let (_, strategy) = kept.as_mut().unwrap();
+        search(strategy.as_mut())
+    }
+
     pub fn default_name() -> String {
         String::from("AI")
     }
 }
 
+impl Default for AI {
+    fn default() -> Self {
+        AI::new()
+    }
+}
+
 impl Player for AI {
     fn name(&self) -> String {
         String::from(&self.name)
@@ -90,11 +166,58 @@ impl Player for AI {
 
     /// Calc the algorithm to choose the best move
     fn choose_move(&self, piece: Piece, board: &Board) -> Result<Move, ErrorGame> {
-        adequat_strategy(&board).calc_move(board, Some(piece))
+        self.with_strategy(board, |strategy| strategy.calc_move(board, Some(piece)))
     }
 
     /// Calc the algorithm to choose the worst piece for the opponent
     fn choose_piece_for_opponent(&self, board: &Board) -> Piece {
-        adequat_strategy(&board).choose_piece_for_opponent(board)
+        self.with_strategy(board, |strategy| strategy.choose_piece_for_opponent(board))
+    }
+
+    /// The AI never misses a Quarto. The lines are only read, the kept strategy is left for the next search
+    fn claim_quarto(&self, board: &Board, last_move: &Move) -> Option<Vec<usize>> {
+        completed_line(board, last_move)
     }
-}
\ No newline at end of file
+
+    fn budget(&self) -> Option<Budget> {
+        self.budget.as_ref().map(|(budget, _)| *budget)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Cell;
+
+    fn kept(ai: &AI) -> Option<(StrategyKind, *const ())> {
+        let kept = ai.strategy.0.borrow();
+        kept.as_ref()
+            .map(|(kind, strategy)| (*kind, strategy.as_ref() as *const dyn Strategy as *const ()))
+    }
+
+    #[test]
+    fn test_ai_keeps_its_strategy_while_the_kind_is_the_same() -> Result<(), ErrorGame> {
+        let mut board = Board::create();
+        for (piece, cell) in [("DFXC", 0), ("DETS", 1), ("WFTC", 6), ("DFTC", 7), ("WEXS", 9), ("WETC", 10)] {
+            board.play_and_remove_piece(&Move::new(Piece::from(piece), Cell::new(cell)?))?;
+        }
+
+        let ai = AI::new();
+        let m = ai.choose_move(*board.get_available_pieces().values().next().unwrap(), &board)?;
+        let searched = kept(&ai);
+        assert_eq!(searched.map(|(kind, _)| kind), Some(StrategyKind::AlphaBeta(3)));
+
+        // The piece is chosen by the same strategy, with the table of the move
+        board.play_and_remove_piece(&m)?;
+        let piece = ai.choose_piece_for_opponent(&board);
+        assert_eq!(kept(&ai), searched);
+
+        // Near the end, the game is solved
+        let m = ai.choose_move(piece, &board)?;
+        board.play_and_remove_piece(&m)?;
+        ai.choose_piece_for_opponent(&board);
+        assert_eq!(kept(&ai).map(|(kind, _)| kind), Some(StrategyKind::Solver));
+
+        Ok(())
+    }
+}
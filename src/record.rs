@@ -0,0 +1,483 @@
+//! # Game record
+//! A PGN-like text format to archive the games: the header tags, then one line per turn
+//! with the piece given and the cell (numbered from 1) where it has been placed.
+//!
+//! ```text
+//! [P1 "Romain"]
+//! [P1Type "Human"]
+//! [P2 "AI"]
+//! [P2Type "AI"]
+//! [Date "2022.09.18"]
+//! [Rules "classic"]
+//! [Result "0-1"]
+//!
+//! 1. DEXC 1
+//! 2. WFTS 6
+//! ```
+//! A piece given but not placed yet is written as a turn without cell: `3. WETC`.
+//! When the rules ask to call "Quarto!", the claim is written after the turns: `Quarto! 2 1 2 3 4`
+//! (the player, then the cells of the line)
+
+use std::{fmt::Display, str::FromStr};
+
+use crate::{
+    board::Cell,
+    error::ErrorGame,
+    game::{Game, Phase},
+    piece::Piece,
+    player::{Human, Player, PlayerType, AI},
+    rules::Rules,
+};
+
+/// The date written when it is not known
+pub const UNKNOWN_DATE: &str = "????.??.??";
+
+/// The record of a game: the tags describing the game and the turns to replay it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameRecord {
+    /// The header tags (name, value), in the order they are written
+    tags: Vec<(String, String)>,
+
+    /// The piece given and the cell where it has been placed, for each turn
+    turns: Vec<(Piece, usize)>,
+
+    /// The piece given after the last turn, which has not been placed
+    gift: Option<Piece>,
+
+    /// The player (by index) who called "Quarto!" and the cells of the line
+    claim: Option<(usize, Vec<usize>)>,
+}
+
+impl GameRecord {
+    /// Record the turns played in the game (the undone turns are not recorded)
+    pub fn from_game(game: &Game) -> GameRecord {
+        let player_tags = |index: usize| {
+            let player = game.get_player(index);
+            let prefix = format!("P{}", index + 1);
+            [
+                (prefix.clone(), player.name()),
+                (format!("{}Type", prefix), player_type_to_text(&player.player_type()).to_string()),
+            ]
+        };
+
+        let mut tags: Vec<(String, String)> = player_tags(0).into_iter().chain(player_tags(1)).collect();
+        tags.push(("Date".to_string(), UNKNOWN_DATE.to_string()));
+        tags.push(("Rules".to_string(), game.rules().to_string()));
+        tags.push(("Result".to_string(), result_of(game).to_string()));
+
+        // With the call Quarto rule, the winner had to claim his line
+        let claim = match game.outcome() {
+            Some(outcome) if game.rules().call_quarto() => outcome.winner().map(|winner| {
+                let line = outcome.winning_lines().first().map(|line| line.keys().copied().collect());
+                (winner, line.unwrap_or_default())
+            }),
+            _ => None,
+        };
+
+        GameRecord {
+            tags,
+            turns: game
+                .history()
+                .iter()
+                .map(|turn| (turn.played_move().piece(), turn.played_move().cell().to_index()))
+                .collect(),
+            gift: game.selected_piece().or(game.outcome().and_then(|outcome| outcome.given_piece())),
+            claim,
+        }
+    }
+
+    /// Set the date of the game (written as "YYYY.MM.DD"), the record of a game is dated `UNKNOWN_DATE` otherwise
+    pub fn with_date(mut self, date: &str) -> GameRecord {
+        self.set_date(date);
+        self
+    }
+
+    /// Set the value of a tag, the tag is added at the end if it doesn't exist yet
+    pub fn with_tag(mut self, name: &str, value: &str) -> GameRecord {
+        self.set_tag(name, value);
+        self
+    }
+
+    /// Set the date of the game (written as "YYYY.MM.DD")
+    pub fn set_date(&mut self, date: &str) {
+        self.set_tag("Date", date);
+    }
+
+    /// Set the value of a tag, the tag is added at the end if it doesn't exist yet
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, tag_value)) => *tag_value = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// The value of a tag
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The number of turns recorded
+    pub fn nb_turns(&self) -> usize {
+        self.turns.len()
+    }
+
+    /// Rebuild the game by replaying the turns of the record
+    pub fn to_game(&self) -> Result<Game, ErrorGame> {
+        let rules = match self.tag("Rules") {
+            Some(rules) => rules.parse::<Rules>()?,
+            None => Rules::default(),
+        };
+        let mut game = Game::start_dyn_with_rules(self.player(0)?, self.player(1)?, rules);
+
+        for (i, (piece, cell)) in self.turns.iter().enumerate() {
+            Cell::from_index(game.get_board(), *cell)
+                .and_then(|cell| game.play(*piece, cell))
+                .map_err(|e| ErrorGame::IllegalTurn(i + 1, Box::new(e)))?;
+        }
+
+        // The piece given is the start of the next turn, the claim is made during the last turn
+        let mut turn = self.turns.len();
+        if let Some(piece) = self.gift {
+            turn += 1;
+            game.give_piece(piece).map_err(|e| ErrorGame::IllegalTurn(turn, Box::new(e)))?;
+        }
+
+        if let Some((player, line)) = &self.claim {
+            game.claim_quarto(*player, line).map_err(|e| ErrorGame::IllegalTurn(turn, Box::new(e)))?;
+        } else if *game.phase() == Phase::AwaitingClaim && self.tag("Result") == Some(DRAW) {
+            game.decline_claim()?;
+        }
+
+        if let Some(result) = self.tag("Result") {
+            if result != result_of(&game) {
+                return Err(ErrorGame::InvalidRecord(format!(
+                    "the result is {} but the turns give {}",
+                    result,
+                    result_of(&game)
+                )));
+            }
+        }
+
+        Ok(game)
+    }
+
+    /// Create the player from the tags, an AI is created with its default settings
+    fn player(&self, index: usize) -> Result<Box<dyn Player>, ErrorGame> {
+        let prefix = format!("P{}", index + 1);
+        let name = self.tag(&prefix).unwrap_or(&prefix);
+
+        match self.tag(&format!("{}Type", prefix)).unwrap_or("Human") {
+            "Human" => Ok(Box::new(Human::new(name))),
+            "AI" => Ok(Box::new(AI::new())),
+            player_type => Err(ErrorGame::InvalidRecord(format!("{} is not a player type", player_type))),
+        }
+    }
+}
+
+const FIRST_PLAYER_WINS: &str = "1-0";
+const SECOND_PLAYER_WINS: &str = "0-1";
+const DRAW: &str = "1/2-1/2";
+const IN_PROGRESS: &str = "*";
+
+/// The result of the game, written like in chess
+fn result_of(game: &Game) -> &'static str {
+    match game.outcome() {
+        Some(outcome) => match outcome.winner() {
+            Some(0) => FIRST_PLAYER_WINS,
+            Some(_) => SECOND_PLAYER_WINS,
+            None => DRAW,
+        },
+        None => IN_PROGRESS,
+    }
+}
+
+fn player_type_to_text(player_type: &PlayerType) -> &'static str {
+    match player_type {
+        PlayerType::Human => "Human",
+        PlayerType::AI => "AI",
+    }
+}
+
+/// Write the record
+impl Display for GameRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, value) in &self.tags {
+            writeln!(f, "[{} \"{}\"]", name, value.replace('\\', "\\\\").replace('"', "\\\""))?;
+        }
+        writeln!(f)?;
+
+        for (i, (piece, cell)) in self.turns.iter().enumerate() {
+            writeln!(f, "{}. {} {}", i + 1, piece.as_text(), cell + 1)?;
+        }
+        if let Some(piece) = self.gift {
+            writeln!(f, "{}. {}", self.turns.len() + 1, piece.as_text())?;
+        }
+
+        if let Some((player, line)) = &self.claim {
+            let cells: Vec<String> = line.iter().map(|cell| (cell + 1).to_string()).collect();
+            writeln!(f, "Quarto! {} {}", player + 1, cells.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Read a record written by `Display`.
+/// Only the syntax is checked here, the turns are checked when the game is rebuilt with `to_game`
+impl FromStr for GameRecord {
+    type Err = ErrorGame;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut record = GameRecord {
+            tags: vec![],
+            turns: vec![],
+            gift: None,
+            claim: None,
+        };
+
+        for line in s.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            if record.claim.is_some() {
+                return Err(ErrorGame::InvalidRecord(format!("nothing can follow the claim: {}", line)));
+            }
+
+            if let Some(tag) = line.strip_prefix('[') {
+                if !record.turns.is_empty() {
+                    return Err(ErrorGame::InvalidRecord(format!("the tag {} is after the turns", line)));
+                }
+                record.tags.push(read_tag(tag)?);
+            } else if let Some(claim) = line.strip_prefix("Quarto!") {
+                let numbers = claim
+                    .split_whitespace()
+                    .map(|number| read_number(number, line))
+                    .collect::<Result<Vec<usize>, ErrorGame>>()?;
+                match numbers.split_first() {
+                    Some((player, cells)) if *player < 2 => record.claim = Some((*player, cells.to_vec())),
+                    _ => return Err(ErrorGame::InvalidRecord(format!("the claim {} has no player", line))),
+                }
+            } else {
+                if record.gift.is_some() {
+                    return Err(ErrorGame::InvalidRecord(format!("only the claim can follow the piece given: {}", line)));
+                }
+
+                let turn = record.turns.len() + 1;
+                let (number, piece, cell) = match line.split_whitespace().collect::<Vec<&str>>()[..] {
+                    [number, piece, cell] => (number, piece, Some(cell)),
+                    [number, piece] => (number, piece, None),
+                    _ => {
+                        return Err(ErrorGame::InvalidRecord(format!("the turn {} is not written \"N. PIECE CELL\"", line)))
+                    }
+                };
+                if number != format!("{}.", turn) {
+                    return Err(ErrorGame::InvalidRecord(format!("the turn {} is expected, found {}", turn, line)));
+                }
+
+                let piece = Piece::from_text(piece).map_err(|e| ErrorGame::IllegalTurn(turn, Box::new(e)))?;
+                match cell {
+                    Some(cell) => record.turns.push((piece, read_number(cell, line)?)),
+                    None => record.gift = Some(piece),
+                }
+            }
+        }
+
+        Ok(record)
+    }
+}
+
+/// Read a tag without its opening bracket: Name "value"]
+fn read_tag(tag: &str) -> Result<(String, String), ErrorGame> {
+    let invalid = || ErrorGame::InvalidRecord(format!("the tag [{} is not written [Name \"value\"]", tag));
+
+    let (name, value) = tag.strip_suffix(']').and_then(|tag| tag.split_once(' ')).ok_or_else(invalid)?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or_else(invalid)?;
+
+    // Unescape the quotes and the backslashes
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        unescaped.push(if c == '\\' { chars.next().ok_or_else(invalid)? } else { c });
+    }
+
+    Ok((name.to_string(), unescaped))
+}
+
+/// Read a number written from 1 (a player or a cell) as an index
+fn read_number(number: &str, line: &str) -> Result<usize, ErrorGame> {
+    match number.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n - 1),
+        _ => Err(ErrorGame::InvalidRecord(format!("{} is not a number in {}", number, line))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(game: &mut Game, turns: &[(&str, usize)]) -> Result<(), ErrorGame> {
+        for (piece, cell) in turns {
+            game.play(Piece::from(*piece), Cell::from_index(game.get_board(), *cell)?)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_record() -> Result<(), ErrorGame> {
+        let mut game = Game::start(Human::new("Romain \"the\" player"), AI::new());
+        play(&mut game, &[("DEXC", 0), ("DFXC", 1), ("DETC", 2), ("DFTC", 3)])?;
+
+        let record = GameRecord::from_game(&game).with_date("2022.09.18");
+        assert_eq!(record.tag("Result"), Some("0-1"));
+        assert_eq!(
+            record.to_string(),
+            "[P1 \"Romain \\\"the\\\" player\"]\n\
+             [P1Type \"Human\"]\n\
+             [P2 \"AI\"]\n\
+             [P2Type \"AI\"]\n\
+             [Date \"2022.09.18\"]\n\
+             [Rules \"classic\"]\n\
+             [Result \"0-1\"]\n\
+             \n\
+             1. DEXC 1\n\
+             2. DFXC 2\n\
+             3. DETC 3\n\
+             4. DFTC 4\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_record() -> Result<(), ErrorGame> {
+        let mut game = Game::start_with_rules(Human::new("p1"), Human::new("p2"), Rules::advanced());
+        play(&mut game, &[("DEXC", 0), ("WFTS", 6), ("DETC", 4)])?;
+
+        let text = GameRecord::from_game(&game).to_string();
+        let record: GameRecord = text.parse()?;
+        assert_eq!(record.tag("Date"), Some(UNKNOWN_DATE));
+        assert_eq!(record.nb_turns(), 3);
+
+        let replayed = record.to_game()?;
+        assert_eq!(replayed.get_player(1).name(), "p2");
+        assert_eq!(replayed.rules(), Rules::advanced());
+        assert_eq!(replayed.history(), game.history());
+        assert!(replayed.get_board() == game.get_board());
+        assert!(!replayed.is_over());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_record_with_claim() -> Result<(), ErrorGame> {
+        let mut game = Game::start_with_rules(
+            Human::new("p1"),
+            Human::new("p2"),
+            Rules::classic().with_call_quarto(true),
+        );
+        play(&mut game, &[("DEXC", 0), ("DFXC", 1), ("DETC", 2), ("DFTC", 3)])?;
+        game.claim_quarto(1, &[0, 1, 2, 3])?;
+
+        let text = GameRecord::from_game(&game).to_string();
+        assert!(text.ends_with("4. DFTC 4\nQuarto! 2 1 2 3 4\n"));
+
+        let replayed = text.parse::<GameRecord>()?.to_game()?;
+        assert_eq!(replayed.outcome(), game.outcome());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_record_with_missed_quarto_claimed() -> Result<(), ErrorGame> {
+        let mut game = Game::start_with_rules(
+            Human::new("p1"),
+            Human::new("p2"),
+            Rules::classic().with_call_quarto(true),
+        );
+        play(&mut game, &[("DEXC", 0), ("DFXC", 1), ("DETC", 2), ("DFTC", 3)])?;
+        // p2 missed his Quarto and gives a piece, p1 claims it
+        game.give_piece(Piece::from("WEXC"))?;
+        game.claim_quarto(0, &[0, 1, 2, 3])?;
+
+        let text = GameRecord::from_game(&game).to_string();
+        assert!(text.ends_with("4. DFTC 4\n5. WEXC\nQuarto! 1 1 2 3 4\n"));
+
+        let replayed = text.parse::<GameRecord>()?.to_game()?;
+        assert_eq!(replayed.outcome(), game.outcome());
+
+        // Without the gift, the claim of p1 comes too early
+        let record: GameRecord = text.replace("5. WEXC\n", "").parse()?;
+        assert_eq!(
+            record.to_game().err(),
+            Some(ErrorGame::IllegalTurn(4, Box::new(ErrorGame::ClaimNotAllowed)))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_record_with_piece_given() -> Result<(), ErrorGame> {
+        let mut game = Game::from(("p1", "p2"));
+        play(&mut game, &[("DEXC", 0)])?;
+        game.give_piece(Piece::from("WFTS"))?;
+
+        let record = GameRecord::from_game(&game).with_date("2022.09.18");
+        let text = record.to_string();
+        assert!(text.contains("[Date \"2022.09.18\"]\n"));
+        assert!(text.ends_with("1. DEXC 1\n2. WFTS\n"));
+
+        let read: GameRecord = text.parse()?;
+        assert_eq!(read, record);
+        assert_eq!(read.tag("Date"), Some("2022.09.18"));
+        assert_eq!(read.to_game()?.selected_piece(), Some(Piece::from("WFTS")));
+
+        // Only the claim can follow the piece given
+        assert!(matches!(
+            format!("{}3. DFTC 2\n", text).parse::<GameRecord>(),
+            Err(ErrorGame::InvalidRecord(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupted_record() {
+        let header = "[P1 \"p1\"]\n[P2 \"p2\"]\n[Result \"*\"]\n\n";
+
+        // The cell 1 is already used by the first turn
+        let record: GameRecord = format!("{}1. DEXC 1\n2. WFTS 1\n", header).parse().unwrap();
+        assert_eq!(
+            record.to_game().err(),
+            Some(ErrorGame::IllegalTurn(2, Box::new(ErrorGame::CellIsNotEmpty(0, "DEXC".to_string()))))
+        );
+
+        // The piece has already been played
+        let record: GameRecord = format!("{}1. DEXC 1\n2. DEXC 2\n", header).parse().unwrap();
+        assert_eq!(
+            record.to_game().err(),
+            Some(ErrorGame::IllegalTurn(2, Box::new(ErrorGame::PieceDoesNotBelongPlayable)))
+        );
+
+        // The cell is out of the board
+        let record: GameRecord = format!("{}1. DEXC 17\n", header).parse().unwrap();
+        assert_eq!(
+            record.to_game().err(),
+            Some(ErrorGame::IllegalTurn(1, Box::new(ErrorGame::IndexOutOfBound)))
+        );
+
+        // The result doesn't match the turns
+        let record: GameRecord = "[Result \"1-0\"]\n1. DEXC 1\n".parse().unwrap();
+        assert!(matches!(record.to_game(), Err(ErrorGame::InvalidRecord(_))));
+
+        // Syntax errors
+        assert!(matches!("[P1 p1]".parse::<GameRecord>(), Err(ErrorGame::InvalidRecord(_))));
+        assert!(matches!("2. DEXC 1".parse::<GameRecord>(), Err(ErrorGame::InvalidRecord(_))));
+        assert!(matches!("1. DEXC 1 2".parse::<GameRecord>(), Err(ErrorGame::InvalidRecord(_))));
+        assert!(matches!("1. DEXC 0".parse::<GameRecord>(), Err(ErrorGame::InvalidRecord(_))));
+        assert!(matches!("1. DEXZ 1".parse::<GameRecord>(), Err(ErrorGame::IllegalTurn(1, _))));
+        assert!(matches!("[Rules \"chess\"]".parse::<GameRecord>().unwrap().to_game(), Err(ErrorGame::UnknownRules(_))));
+    }
+}
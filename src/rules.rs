@@ -0,0 +1,275 @@
+//! # Rules
+//! The variants of the game, which change the lines a player can complete to win
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::error::ErrorGame;
+
+/// Return the mask of the cells (x; y) of a board with `width` columns
+fn mask(width: usize, cells: impl Iterator<Item = (usize, usize)>) -> u64 {
+    cells.fold(0, |mask, (x, y)| mask | (1 << (y * width + x)))
+}
+
+/// The rows first, then the columns, then the two diagonals (only for a square board)
+fn classic_lines(width: usize, height: usize) -> Vec<u64> {
+    let mut lines: Vec<u64> = (0..height)
+        .map(|y| mask(width, (0..width).map(|x| (x, y))))
+        .chain((0..width).map(|x| mask(width, (0..height).map(|y| (x, y)))))
+        .collect();
+
+    if width == height {
+        // Top left to bottom right, then top right to bottom left
+        lines.push(mask(width, (0..width).map(|i| (i, i))));
+        lines.push(mask(width, (0..width).map(|i| (width - 1 - i, i))));
+    }
+    lines
+}
+
+/// The 2x2 squares of the board, from the top left one
+fn squares(width: usize, height: usize) -> Vec<u64> {
+    (0..height.saturating_sub(1))
+        .flat_map(|y| {
+            (0..width.saturating_sub(1))
+                .map(move |x| mask(width, [(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)].into_iter()))
+        })
+        .collect()
+}
+
+/// The diagonals which wrap around the edges of a square board (the two main diagonals are excluded)
+fn broken_diagonals(width: usize, height: usize) -> Vec<u64> {
+    if width != height {
+        return vec![];
+    }
+
+    // Top left to bottom right, then top right to bottom left
+    (1..width)
+        .map(|k| mask(width, (0..width).map(|y| ((y + k) % width, y))))
+        .chain((1..width).map(|k| mask(width, (0..width).map(|y| ((width - 1 - y + k) % width, y)))))
+        .collect()
+}
+
+/// The rules used by a board.
+/// The default rules are the classic ones: only rows, columns and the two diagonals win.
+/// The misère rules can't be played with the call of Quarto (nobody wants to call it):
+/// `with_misere(true)` removes the call, and `with_call_quarto(true)` is ignored with the misère rules
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rules {
+    /// Four pieces sharing an attribute in a 2x2 square also win (advanced rules)
+    square_wins: bool,
+
+    /// The lines wrap around the edges of the board, so the broken diagonals also win
+    toroidal: bool,
+
+    /// A completed line only wins if a player calls "Quarto!" with it.
+    /// A missed win can be claimed by the opponent before his next placement
+    call_quarto: bool,
+
+    /// Misère Quarto: the player who completes a line loses the game
+    misere: bool,
+}
+
+impl Rules {
+    /// The classic rules
+    pub fn classic() -> Rules {
+        Rules::default()
+    }
+
+    /// The advanced rules of the official game, with the 2x2 squares
+    pub fn advanced() -> Rules {
+        Rules::classic().with_square_wins(true)
+    }
+
+    pub fn with_square_wins(mut self, square_wins: bool) -> Rules {
+        self.square_wins = square_wins;
+        self
+    }
+
+    pub fn with_toroidal(mut self, toroidal: bool) -> Rules {
+        self.toroidal = toroidal;
+        self
+    }
+
+    pub fn with_call_quarto(mut self, call_quarto: bool) -> Rules {
+        self.call_quarto = call_quarto && !self.misere;
+        self
+    }
+
+    pub fn with_misere(mut self, misere: bool) -> Rules {
+        self.misere = misere;
+        self.call_quarto &= !misere;
+        self
+    }
+
+    pub fn square_wins(&self) -> bool {
+        self.square_wins
+    }
+
+    pub fn toroidal(&self) -> bool {
+        self.toroidal
+    }
+
+    /// Nobody wants to call Quarto in misère, so the rule is ignored
+    pub fn call_quarto(&self) -> bool {
+        self.call_quarto && !self.misere
+    }
+
+    pub fn misere(&self) -> bool {
+        self.misere
+    }
+
+    /// Return the mask of every line which can be completed to win on a board of `width` x `height` cells
+    pub fn lines(&self, width: usize, height: usize) -> Vec<u64> {
+        let mut lines = classic_lines(width, height);
+        if self.square_wins {
+            lines.extend(squares(width, height));
+        }
+        if self.toroidal {
+            lines.extend(broken_diagonals(width, height));
+        }
+        lines
+    }
+
+    /// The same lines as `lines`, built once for each rules and board size and shared by the boards
+    pub(crate) fn shared_lines(&self, width: usize, height: usize) -> Arc<[u64]> {
+        type Lines = HashMap<(Rules, usize, usize), Arc<[u64]>>;
+        static LINES: OnceLock<Mutex<Lines>> = OnceLock::new();
+
+        let mut lines = LINES.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+        lines
+            .entry((*self, width, height))
+            .or_insert_with(|| self.lines(width, height).into())
+            .clone()
+    }
+}
+
+impl Display for Rules {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut variants = vec![];
+        if self.square_wins {
+            variants.push("advanced");
+        }
+        if self.toroidal {
+            variants.push("toroidal");
+        }
+        if self.call_quarto {
+            variants.push("call-quarto");
+        }
+        if self.misere {
+            variants.push("misere");
+        }
+
+        if variants.is_empty() {
+            write!(f, "classic")
+        } else {
+            write!(f, "{}", variants.join("+"))
+        }
+    }
+}
+
+/// Read the rules written by `Display`, like "advanced+toroidal"
+impl FromStr for Rules {
+    type Err = ErrorGame;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "classic" {
+            return Ok(Rules::classic());
+        }
+
+        let variants: Vec<&str> = s.split('+').collect();
+        if variants.contains(&"call-quarto") && variants.contains(&"misere") {
+            return Err(ErrorGame::UnknownRules(s.to_string()));
+        }
+
+        variants.into_iter().try_fold(Rules::classic(), |rules, variant| match variant {
+            "advanced" => Ok(rules.with_square_wins(true)),
+            "toroidal" => Ok(rules.with_toroidal(true)),
+            "call-quarto" => Ok(rules.with_call_quarto(true)),
+            "misere" => Ok(rules.with_misere(true)),
+            _ => Err(ErrorGame::UnknownRules(s.to_string())),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{broken_diagonals, Rules};
+
+    #[test]
+    fn test_lines_from_rules() {
+        assert_eq!(
+            Rules::classic().lines(4, 4),
+            vec![0x000F, 0x00F0, 0x0F00, 0xF000, 0x1111, 0x2222, 0x4444, 0x8888, 0x8421, 0x1248]
+        );
+        assert_eq!(Rules::advanced().lines(4, 4).len(), 19);
+        assert!(Rules::advanced().lines(4, 4).contains(&0b0000_0000_0011_0011));
+        assert!(Rules::advanced().lines(4, 4).iter().all(|line| line.count_ones() == 4));
+        assert_eq!(Rules::classic().with_toroidal(true).lines(4, 4).len(), 16);
+        assert_eq!(Rules::advanced().with_toroidal(true).lines(4, 4).len(), 25);
+        assert_eq!(*Rules::advanced().shared_lines(4, 4), *Rules::advanced().lines(4, 4));
+    }
+
+    #[test]
+    fn test_lines_from_board_size() {
+        assert_eq!(Rules::classic().lines(3, 3).len(), 8);
+        assert!(Rules::classic().lines(3, 3).iter().all(|line| line.count_ones() == 3));
+        assert_eq!(Rules::advanced().lines(5, 5).len(), 12 + 16);
+        // No diagonal on a rectangular board
+        assert_eq!(Rules::classic().with_toroidal(true).lines(5, 4).len(), 9);
+    }
+
+    #[test]
+    fn test_broken_diagonals_have_one_cell_per_row_and_column() {
+        let broken_diagonals = broken_diagonals(4, 4);
+        assert_eq!(broken_diagonals, vec![0x1842, 0x2184, 0x4218, 0x2481, 0x4812, 0x8124]);
+
+        for line in broken_diagonals {
+            for i in 0..4 {
+                assert_eq!(((line >> (i * 4)) & 0xF).count_ones(), 1);
+                assert_eq!((line & (0x1111 << i)).count_ones(), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_display_rules() {
+        assert_eq!(Rules::classic().to_string(), "classic");
+        assert_eq!(Rules::advanced().to_string(), "advanced");
+        assert_eq!(Rules::classic().with_toroidal(true).to_string(), "toroidal");
+        assert_eq!(
+            Rules::advanced().with_toroidal(true).to_string(),
+            "advanced+toroidal"
+        );
+        assert_eq!(
+            Rules::classic().with_call_quarto(true).to_string(),
+            "call-quarto"
+        );
+        assert_eq!(
+            Rules::advanced().with_misere(true).to_string(),
+            "advanced+misere"
+        );
+    }
+
+    #[test]
+    fn test_rules_from_str() {
+        for rules in [
+            Rules::classic(),
+            Rules::advanced(),
+            Rules::advanced().with_toroidal(true),
+            Rules::classic().with_call_quarto(true),
+            Rules::classic().with_misere(true),
+        ] {
+            assert_eq!(rules.to_string().parse::<Rules>(), Ok(rules));
+        }
+
+        // There is no call of Quarto with the misère rules
+        assert!("call-quarto+misere".parse::<Rules>().is_err());
+        assert_eq!(Rules::classic().with_call_quarto(true).with_misere(true), Rules::classic().with_misere(true));
+        assert_eq!(Rules::classic().with_misere(true).with_call_quarto(true), Rules::classic().with_misere(true));
+        assert!("advanced+".parse::<Rules>().is_err());
+        assert!("chess".parse::<Rules>().is_err());
+    }
+}
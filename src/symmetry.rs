@@ -0,0 +1,316 @@
+//! # Symmetries
+//! Many positions are the same game: the board can be rotated or mirrored, and for the classic board
+//! the inner and outer rings or the middle rows and columns can be swapped, as long as the winning lines stay lines.
+//! The attributes of the pieces can also be permuted and their values flipped.
+//! `canonical` chooses one representative for all the equivalent positions.
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use crate::{
+    board::{GenericBoard, GenericCell, HEIGHT_BOARD, NB_ATTRIBUTES, WIDTH_BOARD},
+    piece::GenericPiece,
+    r#move::GenericMove,
+};
+
+/// A symmetry of the board: a permutation of the cells which keeps the winning lines,
+/// with a permutation of the attributes and the attributes whose values are flipped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenericTransform<const W: usize, const H: usize, const A: usize> {
+    /// The cell `i` goes to the cell `cells[i]`
+    cells: Vec<usize>,
+
+    /// The attribute `j` of the transformed piece is the attribute `attributes[j]` of the piece
+    attributes: [usize; A],
+
+    /// Bit `j` is set when the value of the attribute `j` of the transformed piece is flipped
+    flips: u8,
+}
+
+/// A symmetry of the classic Quarto board
+pub type Transform = GenericTransform<WIDTH_BOARD, HEIGHT_BOARD, NB_ATTRIBUTES>;
+
+/// All the permutations of 0..n, the identity first
+fn permutations(n: usize) -> Vec<Vec<usize>> {
+    if n == 0 {
+        return vec![vec![]];
+    }
+
+    permutations(n - 1)
+        .into_iter()
+        .flat_map(|permutation| {
+            (0..n).rev().map(move |position| {
+                let mut permutation = permutation.clone();
+                permutation.insert(position, n - 1);
+                permutation
+            })
+        })
+        .collect()
+}
+
+impl<const W: usize, const H: usize, const A: usize> GenericTransform<W, H, A> {
+    /// The transform which changes nothing
+    pub fn identity() -> Self {
+        GenericTransform {
+            cells: (0..W * H).collect(),
+            attributes: std::array::from_fn(|attribute| attribute),
+            flips: 0,
+        }
+    }
+
+    /// The permutations of the cells which keep the lines, the identity first.
+    /// They permute the columns and the rows (and transpose the board when it's a square one)
+    pub fn geometric_symmetries(lines: &[u64]) -> Vec<Vec<usize>> {
+        let transposes: &[bool] = if W == H { &[false, true] } else { &[false] };
+        let columns = permutations(W);
+        let rows = permutations(H);
+
+        let mut symmetries = vec![];
+        for transpose in transposes {
+            for column in &columns {
+                for row in &rows {
+                    let cells: Vec<usize> = (0..W * H)
+                        .map(|i| {
+                            let (x, y) = if *transpose { (i / W, i % W) } else { (i % W, i / W) };
+                            row[y] * W + column[x]
+                        })
+                        .collect();
+
+                    let keeps_lines = lines
+                        .iter()
+                        .all(|line| lines.contains(&Self::permute_mask(&cells, *line)));
+                    if keeps_lines {
+                        symmetries.push(cells);
+                    }
+                }
+            }
+        }
+        symmetries
+    }
+
+    /// Every transform of the board: the geometric symmetries combined with the permutations and flips of the attributes
+    pub fn all(lines: &[u64]) -> Vec<Self> {
+        let attributes: Vec<[usize; A]> = permutations(A)
+            .into_iter()
+            .map(|permutation| std::array::from_fn(|attribute| permutation[attribute]))
+            .collect();
+
+        Self::geometric_symmetries(lines)
+            .into_iter()
+            .flat_map(|cells| {
+                let attributes = &attributes;
+                attributes.iter().flat_map(move |permutation| {
+                    let cells = cells.clone();
+                    (0..1u8 << A).map(move |flips| GenericTransform {
+                        cells: cells.clone(),
+                        attributes: *permutation,
+                        flips,
+                    })
+                })
+            })
+            .collect()
+    }
+
+    /// The same transforms as `all`, built once for each size of board and set of lines.
+    /// `canonical` is called for each position of the opening book, so they are not built again at each call
+    pub fn shared(lines: &[u64]) -> Arc<Vec<Self>> {
+        // A static is shared by all the sizes of board, so the transforms are stored without their type
+        type Transforms = HashMap<(TypeId, Vec<u64>), Arc<dyn Any + Send + Sync>>;
+        static TRANSFORMS: OnceLock<Mutex<Transforms>> = OnceLock::new();
+
+        let mut transforms = TRANSFORMS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+        let shared = transforms
+            .entry((TypeId::of::<Self>(), lines.to_vec()))
+            .or_insert_with(|| Arc::new(Self::all(lines)));
+        Arc::clone(shared).downcast().expect("the transforms are stored with the type of their board")
+    }
+
+    fn permute_mask(cells: &[usize], mask: u64) -> u64 {
+        cells
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| mask & (1 << i) != 0)
+            .fold(0, |permuted, (_, cell)| permuted | (1 << cell))
+    }
+
+    /// The transform which takes back this one
+    pub fn inverse(&self) -> Self {
+        let mut cells = vec![0; W * H];
+        for (i, cell) in self.cells.iter().enumerate() {
+            cells[*cell] = i;
+        }
+
+        let mut attributes = [0; A];
+        let mut flips = 0;
+        for (attribute, from) in self.attributes.iter().enumerate() {
+            attributes[*from] = attribute;
+            if self.flips & (1 << attribute) != 0 {
+                flips |= 1 << from;
+            }
+        }
+
+        GenericTransform { cells, attributes, flips }
+    }
+
+    /// The index of the cell where the cell `index` goes
+    pub fn apply_cell_index(&self, index: usize) -> usize {
+        self.cells[index]
+    }
+
+    /// The transformed piece
+    pub fn apply_piece(&self, piece: GenericPiece<A>) -> GenericPiece<A> {
+        let number = (0..A).fold(0, |number, attribute| {
+            let value = piece.has_attribute(self.attributes[attribute]) ^ (self.flips & (1 << attribute) != 0);
+            (number << 1) | value as u8
+        });
+        GenericPiece::from_number(number).unwrap()
+    }
+
+    /// The transformed move: the transformed piece on the transformed cell
+    pub fn apply_move(&self, m: &GenericMove<W, H, A>) -> GenericMove<W, H, A> {
+        let cell = GenericCell::new(self.apply_cell_index(m.cell().to_index())).unwrap();
+        GenericMove::new(self.apply_piece(m.piece()), cell)
+    }
+
+    /// Take back the transform of a move, to play on the original board a move chosen on the transformed one
+    pub fn revert_move(&self, m: &GenericMove<W, H, A>) -> GenericMove<W, H, A> {
+        self.inverse().apply_move(m)
+    }
+
+    /// The transformed board
+    pub fn apply_board(&self, board: &GenericBoard<W, H, A>) -> GenericBoard<W, H, A> {
+        let mut transformed = GenericBoard::create_with_rules(board.rules());
+
+        for (index, cell) in board.get_cells() {
+            if let Some(piece) = cell.piece() {
+                let cell = GenericCell::new(self.apply_cell_index(index)).unwrap();
+                transformed.play(self.apply_piece(piece), cell).unwrap();
+            }
+        }
+        for number in 0..GenericBoard::<W, H, A>::NB_PIECES {
+            if !board.is_available(number) {
+                let piece = self.apply_piece(GenericPiece::from_number(number as u8).unwrap());
+                transformed.remove(piece).unwrap();
+            }
+        }
+        transformed.set_hand(board.hand().map(|piece| self.apply_piece(piece)));
+
+        transformed
+    }
+
+    /// What is compared to choose the canonical board: the piece number + 1 of each cell (0 when empty),
+    /// then the piece in hand and the available pieces.
+    /// It's read from the masks of the board, without allocation, as it's computed for every transform
+    fn key(&self, board: &GenericBoard<W, H, A>) -> ([u8; u64::BITS as usize], u8, u64) {
+        let mut cells = [0; u64::BITS as usize];
+        for index in (0..W * H).filter(|index| board.occupied_mask() & (1 << index) != 0) {
+            let number = (0..A).fold(0, |number, attribute| {
+                (number << 1) | ((board.attribute_mask(attribute) >> index) & 1) as u8
+            });
+            let piece = GenericPiece::from_number(number).unwrap();
+            cells[self.apply_cell_index(index)] = self.apply_piece(piece).number() + 1;
+        }
+
+        let available = (0..GenericBoard::<W, H, A>::NB_PIECES)
+            .filter(|number| board.available_mask() & (1 << number) != 0)
+            .map(|number| self.apply_piece(GenericPiece::from_number(number as u8).unwrap()).number())
+            .fold(0, |available, number| available | (1 << number));
+
+        (
+            cells,
+            board.hand().map_or(0, |piece| self.apply_piece(piece).number() + 1),
+            available,
+        )
+    }
+}
+
+impl<const W: usize, const H: usize, const A: usize> GenericBoard<W, H, A> {
+    /// The canonical board of all the boards equivalent to this one, and the transform from this board to it.
+    /// Two equivalent boards have the same canonical board, a move chosen on it is played here with `revert_move`
+    pub fn canonical(&self) -> (Self, GenericTransform<W, H, A>) {
+        let transform = GenericTransform::shared(self.lines())
+            .iter()
+            .min_by_key(|transform| transform.key(self))
+            .cloned()
+            .unwrap_or_else(GenericTransform::identity);
+
+        (transform.apply_board(self), transform)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        board::{Board, Cell},
+        piece::Piece,
+        r#move::Move,
+        rules::Rules,
+    };
+
+    fn board_from(moves: &[(&str, usize)]) -> Board {
+        let mut board = Board::create();
+        for (piece, cell) in moves {
+            board
+                .play_and_remove_piece(&Move::new(Piece::from(*piece), Cell::new(*cell).unwrap()))
+                .unwrap();
+        }
+        board
+    }
+
+    #[test]
+    fn test_geometric_symmetries() {
+        assert_eq!(Transform::geometric_symmetries(Board::create().lines()).len(), 32);
+        assert_eq!(
+            Transform::geometric_symmetries(Board::create_with_rules(Rules::advanced()).lines()).len(),
+            8
+        );
+        assert_eq!(
+            GenericTransform::<3, 3, 3>::geometric_symmetries(GenericBoard::<3, 3, 3>::create().lines()).len(),
+            8
+        );
+        assert_eq!(Transform::all(Board::create().lines()).len(), 32 * 24 * 16);
+        assert_eq!(Transform::all(Board::create().lines())[0], Transform::identity());
+        assert_eq!(*Transform::shared(Board::create().lines()), Transform::all(Board::create().lines()));
+        assert!(Arc::ptr_eq(&Transform::shared(Board::create().lines()), &Transform::shared(Board::create().lines())));
+    }
+
+    #[test]
+    fn test_inverse_transform() {
+        let board = board_from(&[("DEXC", 0), ("WFTS", 6), ("DFTC", 11)]);
+
+        for transform in Transform::all(board.lines()).iter().step_by(97) {
+            let transformed = transform.apply_board(&board);
+            assert_eq!(transformed.board_state(), board.board_state());
+            assert!(transform.inverse().apply_board(&transformed) == board);
+        }
+    }
+
+    #[test]
+    fn test_equivalent_boards_have_the_same_canonical_board() {
+        let board = board_from(&[("DEXC", 0), ("WFTS", 6), ("DFTC", 11)]);
+        // The board rotated by a quarter turn, with the colors flipped
+        let rotated = board_from(&[("WEXC", 3), ("DFTS", 10), ("WFTC", 13)]);
+        let other = board_from(&[("DEXC", 0), ("WFTS", 5), ("DFTC", 11)]);
+
+        let (canonical, _) = board.canonical();
+        assert!(rotated.canonical().0 == canonical);
+        assert!(other.canonical().0 != canonical);
+        assert!(canonical.canonical().0 == canonical);
+    }
+
+    #[test]
+    fn test_move_on_canonical_board() {
+        let board = board_from(&[("DEXC", 0), ("WFTS", 6), ("DFTC", 11)]);
+        let (canonical, transform) = board.canonical();
+
+        for m in canonical.get_available_moves() {
+            let original_move = transform.revert_move(&m);
+            assert!(board.get_available_moves().contains(&original_move));
+            assert_eq!(transform.apply_move(&original_move), m);
+        }
+    }
+}
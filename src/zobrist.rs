@@ -0,0 +1,51 @@
+//! # Zobrist hashing
+//! Each (piece, cell) pair, each played piece and each piece in hand has its own random key.
+//! The hash of a position is the xor of the keys of what it contains, so it's updated with a single xor on each move.
+//! The keys are computed from their index (splitmix64), so they are the same from one run to another
+
+/// The kind of each key, to keep them apart
+const PIECE_ON_CELL: u64 = 0;
+const PIECE_PLAYED: u64 = 1;
+const PIECE_IN_HAND: u64 = 2;
+
+/// A random looking key for each index, always the same (splitmix64)
+const fn key(kind: u64, index: u64) -> u64 {
+    let mut z = ((kind << 32) | index).wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// The key of the piece (by number) on the cell (by index)
+pub(crate) const fn piece_on_cell(cell: usize, piece: u8) -> u64 {
+    key(PIECE_ON_CELL, ((cell as u64) << 8) | piece as u64)
+}
+
+/// The key of a piece which is not available anymore
+pub(crate) const fn piece_played(piece: u8) -> u64 {
+    key(PIECE_PLAYED, piece as u64)
+}
+
+/// The key of the piece handed over to the next player
+pub(crate) const fn piece_in_hand(piece: u8) -> u64 {
+    key(PIECE_IN_HAND, piece as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn test_keys_are_distinct() {
+        let keys: HashSet<u64> = (0..64)
+            .flat_map(|cell| (0..64).map(move |piece| piece_on_cell(cell, piece)))
+            .chain((0..64).map(piece_played))
+            .chain((0..64).map(piece_in_hand))
+            .collect();
+
+        assert_eq!(keys.len(), 64 * 64 + 64 + 64);
+        assert!(!keys.contains(&0));
+    }
+}